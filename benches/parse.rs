@@ -0,0 +1,58 @@
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+
+use npm_dependency_graph::version::{condition::Condition, semver::Version};
+
+const VERSIONS: &[&str] = &[
+    "1.2.3",
+    "0.0.1",
+    "10.20.30",
+    "v1.0.0",
+    "4.17.21",
+    "1.0.0-alpha",
+    "1.0.0-alpha.1",
+    "2.0.0-rc.1+build.123",
+    "1.0.0-x.7.z.92",
+    "3.0.0-beta.2.exp.sha.5114f85",
+];
+
+const CONDITIONS: &[&str] = &[
+    "^1.2.3",
+    "~4.17.0",
+    ">=1.0.0 <2.0.0",
+    "1.x || >=2.5.0 || 5.0.0 - 7.2.3",
+    "*",
+    ">= 2.1.2 < 3.0.0-beta.1",
+    "^0.0.1-rc.3",
+    "~1",
+];
+
+fn parse_versions(c: &mut Criterion) {
+    let mut group = c.benchmark_group("version");
+    group.throughput(Throughput::Elements(VERSIONS.len() as u64));
+    group.bench_function("parse", |b| {
+        b.iter(|| {
+            for input in VERSIONS {
+                black_box(Version::parse(black_box(input)).unwrap());
+            }
+        })
+    });
+    group.finish();
+}
+
+fn parse_conditions(c: &mut Criterion) {
+    let mut group = c.benchmark_group("condition");
+    group.throughput(Throughput::Elements(CONDITIONS.len() as u64));
+    group.bench_function("parse", |b| {
+        b.iter(|| {
+            for input in CONDITIONS {
+                black_box(Condition::parse(black_box(input)).unwrap());
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, parse_versions, parse_conditions);
+criterion_main!(benches);
@@ -0,0 +1,279 @@
+//! Packages and the dependencies between them.
+//!
+//! A node is a package at one version, several versions of a package are
+//! several nodes. An edge is a dependency of one node on another, with the
+//! kind of dependency and the range that picked the version it points at.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::Display;
+use std::ops::Index;
+
+use crate::version::{condition::Condition, semver::Version};
+
+/// Ids index the graph they came from and stay valid as it grows.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NodeId(usize);
+
+/// The id of a dependency in a [`DependencyGraph`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct EdgeId(usize);
+
+/// A package at one version.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Package {
+    /// The name the package is published under.
+    pub name: String,
+    /// The version of the package.
+    pub version: Version,
+}
+
+impl Display for Package {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}@{}", self.name, self.version)
+    }
+}
+
+/// Which list of a manifest a dependency is declared in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DependencyKind {
+    /// `dependencies`.
+    Prod,
+    /// `devDependencies`.
+    Dev,
+    /// `peerDependencies`.
+    Peer,
+    /// `optionalDependencies`.
+    Optional,
+}
+
+/// A dependency of one package on another.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Dependency {
+    /// The package that has the dependency.
+    pub from: NodeId,
+    /// The package it resolved to.
+    pub to: NodeId,
+    /// The name the dependency is declared under, which differs from the name
+    /// of the package it points at for aliases.
+    pub name: String,
+    /// How the dependency is declared.
+    pub kind: DependencyKind,
+    /// The range the version was picked with.
+    pub condition: Condition,
+}
+
+/// Packages and the dependencies between them.
+#[derive(Clone, Debug, Default)]
+pub struct DependencyGraph {
+    nodes: Vec<Package>,
+    edges: Vec<Dependency>,
+    outgoing: Vec<Vec<EdgeId>>,
+    incoming: Vec<Vec<EdgeId>>,
+    // The nodes of each package, sorted by version.
+    by_name: BTreeMap<String, Vec<NodeId>>,
+}
+
+impl DependencyGraph {
+    /// An empty graph.
+    pub fn new() -> Self {
+        DependencyGraph::default()
+    }
+
+    /// Adds a package, or returns the node it already has.
+    pub fn add_package(&mut self, name: &str, version: Version) -> NodeId {
+        if let Some(id) = self.find(name, &version) {
+            return id;
+        }
+
+        let id = NodeId(self.nodes.len());
+        let versions = self.by_name.entry(name.to_owned()).or_default();
+        let position = versions.partition_point(|n| self.nodes[n.0].version < version);
+        versions.insert(position, id);
+
+        self.nodes.push(Package {
+            name: name.to_owned(),
+            version,
+        });
+        self.outgoing.push(vec![]);
+        self.incoming.push(vec![]);
+        id
+    }
+
+    /// Adds an edge from one package to another.
+    pub fn add_dependency(
+        &mut self,
+        from: NodeId,
+        to: NodeId,
+        name: &str,
+        kind: DependencyKind,
+        condition: Condition,
+    ) -> EdgeId {
+        let id = EdgeId(self.edges.len());
+        self.edges.push(Dependency {
+            from,
+            to,
+            name: name.to_owned(),
+            kind,
+            condition,
+        });
+        self.outgoing[from.0].push(id);
+        self.incoming[to.0].push(id);
+        id
+    }
+
+    /// The node of a package at a version, if the graph has it.
+    pub fn find(&self, name: &str, version: &Version) -> Option<NodeId> {
+        self.versions(name)
+            .find(|&id| self.nodes[id.0].version == *version)
+    }
+
+    /// The nodes of a package from its lowest version to its highest.
+    pub fn versions<'g>(&'g self, name: &str) -> impl DoubleEndedIterator<Item = NodeId> + 'g {
+        self.by_name.get(name).into_iter().flatten().copied()
+    }
+
+    /// The names of every package in the graph, in order.
+    pub fn package_names(&self) -> impl Iterator<Item = &str> {
+        self.by_name.keys().map(String::as_str)
+    }
+
+    /// The package a node stands for.
+    pub fn package(&self, id: NodeId) -> &Package {
+        &self.nodes[id.0]
+    }
+
+    /// The dependency an edge stands for.
+    pub fn dependency(&self, id: EdgeId) -> &Dependency {
+        &self.edges[id.0]
+    }
+
+    /// Every node, in the order they were added.
+    pub fn packages(&self) -> impl Iterator<Item = (NodeId, &Package)> {
+        self.nodes.iter().enumerate().map(|(i, p)| (NodeId(i), p))
+    }
+
+    /// Every edge, in the order they were added.
+    pub fn dependencies(&self) -> impl Iterator<Item = (EdgeId, &Dependency)> {
+        self.edges.iter().enumerate().map(|(i, d)| (EdgeId(i), d))
+    }
+
+    /// What a node depends on, in the order the dependencies were added.
+    pub fn dependencies_of(&self, id: NodeId) -> impl Iterator<Item = (EdgeId, &Dependency)> {
+        self.outgoing[id.0].iter().map(|&e| (e, &self.edges[e.0]))
+    }
+
+    /// What depends on a node.
+    pub fn dependents_of(&self, id: NodeId) -> impl Iterator<Item = (EdgeId, &Dependency)> {
+        self.incoming[id.0].iter().map(|&e| (e, &self.edges[e.0]))
+    }
+
+    /// Every node reachable from `root`, `root` included, breadth first.
+    pub fn reachable_from(&self, root: NodeId) -> Vec<NodeId> {
+        let mut seen = vec![false; self.nodes.len()];
+        let mut queue = VecDeque::from([root]);
+        let mut order = vec![];
+        seen[root.0] = true;
+
+        while let Some(id) = queue.pop_front() {
+            order.push(id);
+            for (_, dependency) in self.dependencies_of(id) {
+                if !seen[dependency.to.0] {
+                    seen[dependency.to.0] = true;
+                    queue.push_back(dependency.to);
+                }
+            }
+        }
+
+        order
+    }
+
+    /// The number of nodes.
+    pub fn package_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// The number of edges.
+    pub fn dependency_count(&self) -> usize {
+        self.edges.len()
+    }
+}
+
+impl Index<NodeId> for DependencyGraph {
+    type Output = Package;
+
+    fn index(&self, id: NodeId) -> &Package {
+        self.package(id)
+    }
+}
+
+impl Index<EdgeId> for DependencyGraph {
+    type Output = Dependency;
+
+    fn index(&self, id: EdgeId) -> &Dependency {
+        self.dependency(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(v: &str) -> Version {
+        Version::parse(v).unwrap()
+    }
+
+    fn range(c: &str) -> Condition {
+        Condition::parse(c).unwrap()
+    }
+
+    #[test]
+    fn multiple_versions() {
+        let mut graph = DependencyGraph::new();
+        let b2 = graph.add_package("b", version("2.0.0"));
+        let a = graph.add_package("a", version("1.0.0"));
+        let b1 = graph.add_package("b", version("1.5.0"));
+
+        assert_eq!(graph.add_package("b", version("2.0.0")), b2);
+        assert_eq!(graph.package_count(), 3);
+        assert_eq!(graph.versions("b").collect::<Vec<_>>(), [b1, b2]);
+        assert_eq!(graph.versions("b").next_back(), Some(b2));
+        assert_eq!(graph.versions("c").count(), 0);
+        assert_eq!(graph.find("a", &version("1.0.0")), Some(a));
+        assert_eq!(graph.find("a", &version("1.0.1")), None);
+        assert_eq!(graph.package_names().collect::<Vec<_>>(), ["a", "b"]);
+        assert_eq!(graph[b1].to_string(), "b@1.5.0");
+    }
+
+    #[test]
+    fn edges() {
+        let mut graph = DependencyGraph::new();
+        let root = graph.add_package("root", version("0.0.0"));
+        let a = graph.add_package("a", version("1.0.0"));
+        let b = graph.add_package("b", version("2.1.0"));
+        let c = graph.add_package("c", version("3.0.0"));
+
+        let root_a = graph.add_dependency(root, a, "a", DependencyKind::Prod, range("^1"));
+        let root_b = graph.add_dependency(root, b, "b", DependencyKind::Dev, range("~2.1"));
+        let a_b = graph.add_dependency(a, b, "alias", DependencyKind::Peer, range(">=2"));
+
+        assert_eq!(graph.dependency_count(), 3);
+        assert_eq!(
+            graph
+                .dependencies_of(root)
+                .map(|(e, _)| e)
+                .collect::<Vec<_>>(),
+            [root_a, root_b]
+        );
+        assert_eq!(
+            graph.dependents_of(b).map(|(e, _)| e).collect::<Vec<_>>(),
+            [root_b, a_b]
+        );
+        assert_eq!(graph[a_b].name, "alias");
+        assert_eq!(graph[a_b].kind, DependencyKind::Peer);
+        assert_eq!(graph[root_a].condition, range("^1"));
+        assert_eq!(graph.dependents_of(root).count(), 0);
+
+        assert_eq!(graph.reachable_from(root), [root, a, b]);
+        assert_eq!(graph.reachable_from(c), [c]);
+    }
+}
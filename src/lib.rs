@@ -0,0 +1,28 @@
+//! Reading npm version numbers and ranges, the building blocks of an npm
+//! dependency graph.
+//!
+//! [`version::semver`] holds [`Version`](version::semver::Version), parsed and
+//! ordered the way node-semver does, and [`version::condition`] holds
+//! [`Condition`](version::condition::Condition), a range such as `^1.2.3` or
+//! `>=1.0.0 <2.0.0 || 3.x` that versions are matched against.
+//! [`manifest`] reads the `package.json` that dependencies are declared in,
+//! and [`spec`] the specs they are declared with, ranges or otherwise.
+//! [`graph`] holds the packages a project resolves to and how they depend on
+//! each other, which [`resolver`] works out from a [`registry`] snapshot.
+//!
+//! ```
+//! use npm_dependency_graph::version::{condition::Condition, semver::Version};
+//!
+//! let condition = Condition::parse("^1.2.3").unwrap();
+//! assert!(condition.compare(&Version::parse("1.4.0").unwrap()));
+//! assert!(!condition.compare(&Version::parse("2.0.0").unwrap()));
+//! ```
+
+#![warn(missing_docs)]
+
+pub mod graph;
+pub mod manifest;
+pub mod registry;
+pub mod resolver;
+pub mod spec;
+pub mod version;
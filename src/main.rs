@@ -1,5 +1,44 @@
-mod version;
+use std::process::ExitCode;
 
-fn main() {
-    println!("Hello, world!");
+use npm_dependency_graph::version::{condition::Condition, semver::Version};
+
+// Prints the versions that satisfy a range, like `semver -r <range> <versions>`.
+fn main() -> ExitCode {
+    let mut args = std::env::args().skip(1);
+    let Some(range) = args.next() else {
+        eprintln!("usage: npm-dependency-graph <range> [<version>...]");
+        return ExitCode::FAILURE;
+    };
+
+    let condition = match Condition::parse(&range) {
+        Ok(condition) => condition,
+        Err(e) => {
+            eprintln!("{}", e.snippet());
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut versions = vec![];
+    for arg in args {
+        match Version::parse(&arg) {
+            Ok(version) => versions.push(version),
+            Err(e) => {
+                eprintln!("{}", e.snippet());
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    versions.sort();
+
+    let mut found = false;
+    for version in condition.filter_satisfying(&versions) {
+        println!("{}", version);
+        found = true;
+    }
+
+    if !found {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
 }
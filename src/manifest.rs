@@ -0,0 +1,512 @@
+//! The parts of a `package.json` that matter for resolving dependencies.
+
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+use serde_json::{Map, Value};
+
+use crate::spec::{DependencySpec, SpecError};
+use crate::version::{condition::Condition, semver::Version, ParseError, ParseOptions};
+
+/// A parsed `package.json`. Fields that are missing are left empty.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PackageManifest {
+    /// `name`.
+    pub name: Option<String>,
+    /// `version`.
+    pub version: Option<Version>,
+    /// `dependencies`.
+    pub dependencies: Dependencies,
+    /// `devDependencies`.
+    pub dev_dependencies: Dependencies,
+    /// `peerDependencies`.
+    pub peer_dependencies: Dependencies,
+    /// `optionalDependencies`.
+    pub optional_dependencies: Dependencies,
+    /// `bundleDependencies`, or `bundledDependencies`.
+    pub bundled_dependencies: Vec<String>,
+    /// `peerDependenciesMeta`.
+    pub peer_dependencies_meta: BTreeMap<String, PeerDependencyMeta>,
+    /// `engines`, the range of each runtime the package works with.
+    pub engines: BTreeMap<String, Condition>,
+    /// `os`.
+    pub os: Vec<String>,
+    /// `cpu`.
+    pub cpu: Vec<String>,
+    /// `bin`, by command name.
+    pub bin: BTreeMap<String, String>,
+    /// `workspaces`.
+    pub workspaces: Vec<String>,
+    /// `overrides`.
+    pub overrides: Overrides,
+}
+
+/// Dependencies by the name they are installed under.
+pub type Dependencies = BTreeMap<String, DependencySpec>;
+
+/// What `peerDependenciesMeta` says about a peer dependency.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PeerDependencyMeta {
+    /// The peer is only used when something else installs it.
+    pub optional: bool,
+}
+
+/// An override either replaces the spec of a package wherever it appears, or
+/// applies its own overrides under that package only. `"."` names the spec of
+/// the package itself in the nested form: `{ "foo": { ".": "1.0.0", "bar": "2" } }`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Override {
+    /// What the package is replaced with, if anything.
+    pub spec: Option<OverrideSpec>,
+    /// The overrides that apply under this package.
+    pub overrides: Overrides,
+}
+
+/// What an override replaces a package with.
+#[derive(Clone, Debug, PartialEq)]
+pub enum OverrideSpec {
+    /// A spec of its own.
+    Spec(Box<DependencySpec>),
+    /// `$name` stands for the spec the root manifest gives `name`.
+    Reference(String),
+}
+
+/// Overrides by the name of the package they apply to.
+pub type Overrides = BTreeMap<String, Override>;
+
+// npm normalizes manifests with node-semver's loose mode.
+const LOOSE: ParseOptions = ParseOptions { loose: true };
+
+impl PackageManifest {
+    /// Parses a manifest from its JSON text.
+    pub fn parse(input: &str) -> Result<Self, ManifestError> {
+        let value = serde_json::from_str::<Value>(input)
+            .map_err(|e| ManifestError::new(String::new(), ManifestErrorKind::Json(e)))?;
+        PackageManifest::from_json(&value)
+    }
+
+    /// Reads a manifest out of a document that was already parsed, such as
+    /// one of the versions of a packument.
+    pub fn from_json(value: &Value) -> Result<Self, ManifestError> {
+        let root = as_object(value, "")?;
+        let field = |key: &str| root.get(key).filter(|v| !v.is_null());
+
+        let mut manifest = PackageManifest {
+            name: field("name")
+                .map(|v| as_string(v, "name").map(str::to_owned))
+                .transpose()?,
+            ..Default::default()
+        };
+
+        if let Some(version) = field("version") {
+            let version = as_string(version, "version")?;
+            manifest.version = Some(
+                Version::parse_with_options(version, &LOOSE)
+                    .map_err(|e| ManifestError::new("version", ManifestErrorKind::Version(e)))?,
+            );
+        }
+
+        let dependencies = |key: &str| {
+            field(key)
+                .map(|v| parse_dependencies(v, key))
+                .transpose()
+                .map(Option::unwrap_or_default)
+        };
+        manifest.dependencies = dependencies("dependencies")?;
+        manifest.dev_dependencies = dependencies("devDependencies")?;
+        manifest.peer_dependencies = dependencies("peerDependencies")?;
+        manifest.optional_dependencies = dependencies("optionalDependencies")?;
+
+        // Both spellings are accepted, `true` bundles every dependency.
+        let bundled = ["bundleDependencies", "bundledDependencies"]
+            .into_iter()
+            .find_map(|key| field(key).map(|v| (key, v)));
+        manifest.bundled_dependencies = match bundled {
+            Some((_, Value::Bool(true))) => manifest.dependencies.keys().cloned().collect(),
+            Some((_, Value::Bool(false))) | None => vec![],
+            Some((key, value)) => as_strings(value, key)?,
+        };
+
+        if let Some(meta) = field("peerDependenciesMeta") {
+            for (name, value) in as_object(meta, "peerDependenciesMeta")? {
+                let path = join("peerDependenciesMeta", name);
+                let object = as_object(value, &path)?;
+                let optional = match object.get("optional") {
+                    Some(optional) => as_bool(optional, &join(&path, "optional"))?,
+                    None => false,
+                };
+                manifest
+                    .peer_dependencies_meta
+                    .insert(name.clone(), PeerDependencyMeta { optional });
+            }
+        }
+
+        if let Some(engines) = field("engines") {
+            for (engine, value) in as_object(engines, "engines")? {
+                let path = join("engines", engine);
+                let range = Condition::parse_with_options(as_string(value, &path)?, &LOOSE)
+                    .map_err(|e| ManifestError::new(path, ManifestErrorKind::Range(e)))?;
+                manifest.engines.insert(engine.clone(), range);
+            }
+        }
+
+        manifest.os = field("os")
+            .map(|v| as_list(v, "os"))
+            .transpose()?
+            .unwrap_or_default();
+        manifest.cpu = field("cpu")
+            .map(|v| as_list(v, "cpu"))
+            .transpose()?
+            .unwrap_or_default();
+
+        // A single command is named after the package, without its scope.
+        match field("bin") {
+            Some(Value::String(path)) => {
+                let Some(name) = &manifest.name else {
+                    return Err(ManifestError::new(
+                        "bin",
+                        ManifestErrorKind::Type("an object when there is no name"),
+                    ));
+                };
+                let command = name.rsplit('/').next().unwrap_or(name);
+                manifest.bin.insert(command.to_owned(), path.clone());
+            }
+            Some(bin) => {
+                for (command, path) in as_object(bin, "bin")? {
+                    let path = as_string(path, &join("bin", command))?;
+                    manifest.bin.insert(command.clone(), path.to_owned());
+                }
+            }
+            None => (),
+        }
+
+        // Yarn's object form lists the patterns under `packages`.
+        manifest.workspaces = match field("workspaces") {
+            Some(Value::Object(workspaces)) => match workspaces.get("packages") {
+                Some(packages) => as_strings(packages, "workspaces.packages")?,
+                None => vec![],
+            },
+            Some(workspaces) => as_strings(workspaces, "workspaces")?,
+            None => vec![],
+        };
+
+        if let Some(overrides) = field("overrides") {
+            manifest.overrides = parse_overrides(as_object(overrides, "overrides")?, "overrides")?;
+        }
+
+        Ok(manifest)
+    }
+}
+
+fn parse_dependencies(value: &Value, path: &str) -> Result<Dependencies, ManifestError> {
+    as_object(value, path)?
+        .iter()
+        .map(|(name, spec)| {
+            let path = join(path, name);
+            let spec = DependencySpec::parse(as_string(spec, &path)?)
+                .map_err(|e| ManifestError::new(path, ManifestErrorKind::Spec(e)))?;
+            Ok((name.clone(), spec))
+        })
+        .collect()
+}
+
+fn parse_overrides(object: &Map<String, Value>, path: &str) -> Result<Overrides, ManifestError> {
+    let mut overrides = Overrides::new();
+    for (name, value) in object {
+        let path = join(path, name);
+        let entry = match value {
+            Value::String(spec) => Override {
+                spec: Some(parse_override_spec(spec, &path)?),
+                overrides: Overrides::new(),
+            },
+            Value::Object(nested) => {
+                let mut nested = nested.clone();
+                let spec = nested
+                    .remove(".")
+                    .map(|v| {
+                        let path = join(&path, ".");
+                        parse_override_spec(as_string(&v, &path)?, &path)
+                    })
+                    .transpose()?;
+                Override {
+                    spec,
+                    overrides: parse_overrides(&nested, &path)?,
+                }
+            }
+            _ => {
+                return Err(ManifestError::new(
+                    path,
+                    ManifestErrorKind::Type("a string or an object"),
+                ))
+            }
+        };
+        overrides.insert(name.clone(), entry);
+    }
+    Ok(overrides)
+}
+
+fn parse_override_spec(spec: &str, path: &str) -> Result<OverrideSpec, ManifestError> {
+    if let Some(name) = spec.strip_prefix('$') {
+        return Ok(OverrideSpec::Reference(name.to_owned()));
+    }
+    DependencySpec::parse(spec)
+        .map(|spec| OverrideSpec::Spec(Box::new(spec)))
+        .map_err(|e| ManifestError::new(path, ManifestErrorKind::Spec(e)))
+}
+
+// Paths read like JavaScript property accesses: `dependencies.lodash`, but
+// `dependencies["@types/node"]` for keys that aren't identifiers. Those are
+// quoted as JSON strings, which JavaScript reads the same way.
+pub(crate) fn join(path: &str, key: &str) -> String {
+    let identifier = key.chars().enumerate().all(|(i, c)| {
+        c == '_' || c == '$' || c.is_ascii_alphabetic() || (i > 0 && c.is_ascii_digit())
+    });
+
+    match (path.is_empty(), identifier && !key.is_empty()) {
+        (true, true) => key.to_owned(),
+        (false, true) => format!("{}.{}", path, key),
+        (_, false) => format!("{}[{}]", path, Value::from(key)),
+    }
+}
+
+pub(crate) fn as_object<'v>(
+    value: &'v Value,
+    path: &str,
+) -> Result<&'v Map<String, Value>, ManifestError> {
+    value
+        .as_object()
+        .ok_or_else(|| ManifestError::new(path, ManifestErrorKind::Type("an object")))
+}
+
+pub(crate) fn as_string<'v>(value: &'v Value, path: &str) -> Result<&'v str, ManifestError> {
+    value
+        .as_str()
+        .ok_or_else(|| ManifestError::new(path, ManifestErrorKind::Type("a string")))
+}
+
+fn as_bool(value: &Value, path: &str) -> Result<bool, ManifestError> {
+    value
+        .as_bool()
+        .ok_or_else(|| ManifestError::new(path, ManifestErrorKind::Type("a boolean")))
+}
+
+fn as_strings(value: &Value, path: &str) -> Result<Vec<String>, ManifestError> {
+    let Some(values) = value.as_array() else {
+        return Err(ManifestError::new(
+            path,
+            ManifestErrorKind::Type("an array"),
+        ));
+    };
+    values
+        .iter()
+        .enumerate()
+        .map(|(i, v)| as_string(v, &format!("{}[{}]", path, i)).map(str::to_owned))
+        .collect()
+}
+
+// `os` and `cpu` may name a single platform instead of a list.
+fn as_list(value: &Value, path: &str) -> Result<Vec<String>, ManifestError> {
+    match value {
+        Value::String(value) => Ok(vec![value.clone()]),
+        value => as_strings(value, path),
+    }
+}
+
+/// Why a manifest could not be read.
+#[derive(Debug)]
+pub enum ManifestErrorKind {
+    /// The document is not valid JSON.
+    Json(serde_json::Error),
+    /// The kind of value that was expected, e.g. "a string".
+    Type(&'static str),
+    /// An invalid version.
+    Version(ParseError),
+    /// An invalid range.
+    Range(ParseError),
+    /// An invalid dependency spec.
+    Spec(SpecError),
+}
+
+/// An error reading a manifest, with where in the document it is.
+#[derive(Debug)]
+pub struct ManifestError {
+    path: String,
+    kind: ManifestErrorKind,
+}
+
+impl ManifestError {
+    pub(crate) fn new(path: impl Into<String>, kind: ManifestErrorKind) -> Self {
+        ManifestError {
+            path: path.into(),
+            kind,
+        }
+    }
+
+    // Moves the error under `path`, for manifests nested in other documents.
+    pub(crate) fn within(mut self, path: &str) -> Self {
+        self.path = match self.path.chars().next() {
+            None => path.to_owned(),
+            Some('[') => format!("{}{}", path, self.path),
+            Some(_) => format!("{}.{}", path, self.path),
+        };
+        self
+    }
+
+    /// Where in the document the error is, empty for the document itself.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// What went wrong.
+    pub fn kind(&self) -> &ManifestErrorKind {
+        &self.kind
+    }
+}
+
+impl Display for ManifestError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !self.path.is_empty() {
+            write!(f, "`{}`: ", self.path)?;
+        }
+        match &self.kind {
+            ManifestErrorKind::Json(e) => write!(f, "invalid JSON: {}", e),
+            ManifestErrorKind::Type(expected) => write!(f, "expected {}", expected),
+            ManifestErrorKind::Version(e) => write!(f, "invalid version: {}", e),
+            ManifestErrorKind::Range(e) => write!(f, "invalid range: {}", e),
+            ManifestErrorKind::Spec(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for ManifestError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.kind {
+            ManifestErrorKind::Json(e) => Some(e),
+            ManifestErrorKind::Version(e) | ManifestErrorKind::Range(e) => Some(e),
+            ManifestErrorKind::Spec(e) => Some(e),
+            ManifestErrorKind::Type(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(input: &str) -> DependencySpec {
+        DependencySpec::Range(Condition::parse(input).unwrap())
+    }
+
+    #[test]
+    fn full_manifest() {
+        let manifest = PackageManifest::parse(
+            r#"{
+                "name": "@scope/tool",
+                "version": "v1.2.3-beta.1",
+                "dependencies": { "lodash": "^4.17.21", "local": "file:../local", "any": "" },
+                "devDependencies": { "jest": "latest" },
+                "peerDependencies": { "react": ">=17 <19" },
+                "optionalDependencies": { "fsevents": "~2.3.2" },
+                "bundleDependencies": true,
+                "peerDependenciesMeta": { "react": { "optional": true } },
+                "engines": { "node": ">=18" },
+                "os": ["darwin", "linux"],
+                "cpu": "x64",
+                "bin": "./cli.js",
+                "workspaces": { "packages": ["packages/*"] },
+                "overrides": { "foo": "1.0.0", "bar": { ".": "2.0.0", "baz": "$lodash" } },
+                "description": "ignored"
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(manifest.name.as_deref(), Some("@scope/tool"));
+        assert_eq!(
+            manifest.version,
+            Some(Version::parse("1.2.3-beta.1").unwrap())
+        );
+        assert_eq!(manifest.dependencies["lodash"], range("^4.17.21"));
+        assert_eq!(
+            manifest.dependencies["local"],
+            DependencySpec::File("../local".to_owned())
+        );
+        assert_eq!(
+            manifest.dependencies["any"],
+            DependencySpec::Range(Condition::Any)
+        );
+        assert_eq!(
+            manifest.dev_dependencies["jest"],
+            DependencySpec::Tag("latest".to_owned())
+        );
+        assert_eq!(manifest.peer_dependencies["react"], range(">=17 <19"));
+        assert_eq!(manifest.optional_dependencies["fsevents"], range("~2.3.2"));
+        assert_eq!(manifest.bundled_dependencies, ["any", "local", "lodash"]);
+        assert!(manifest.peer_dependencies_meta["react"].optional);
+        assert_eq!(manifest.engines["node"], Condition::parse(">=18").unwrap());
+        assert_eq!(manifest.os, ["darwin", "linux"]);
+        assert_eq!(manifest.cpu, ["x64"]);
+        assert_eq!(manifest.bin["tool"], "./cli.js");
+        assert_eq!(manifest.workspaces, ["packages/*"]);
+
+        let spec = |input: &str| Some(OverrideSpec::Spec(Box::new(range(input))));
+        assert_eq!(manifest.overrides["foo"].spec, spec("1.0.0"));
+        let bar = &manifest.overrides["bar"];
+        assert_eq!(bar.spec, spec("2.0.0"));
+        assert_eq!(
+            bar.overrides["baz"].spec,
+            Some(OverrideSpec::Reference("lodash".to_owned()))
+        );
+    }
+
+    #[test]
+    fn empty_manifest() {
+        let manifest = PackageManifest::parse("{}").unwrap();
+        assert_eq!(manifest, PackageManifest::default());
+    }
+
+    #[test]
+    fn error_paths() {
+        let error = |input: &str| PackageManifest::parse(input).unwrap_err();
+
+        let e = error(r#"{ "dependencies": { "@types/node": 18 } }"#);
+        assert_eq!(e.path(), r#"dependencies["@types/node"]"#);
+        assert_eq!(
+            e.to_string(),
+            r#"`dependencies["@types/node"]`: expected a string"#
+        );
+
+        let e = error(r#"{ "dependencies": { "a\u001b\"b\u00e9": 18 } }"#);
+        assert_eq!(e.path(), r#"dependencies["a\u001b\"bé"]"#);
+
+        let e = error(r#"{ "devDependencies": { "jest": "not a tag" } }"#);
+        assert_eq!(e.path(), "devDependencies.jest");
+        assert!(matches!(e.kind(), ManifestErrorKind::Spec(_)));
+
+        let e = error(r#"{ "version": "1.2.x" }"#);
+        assert_eq!(e.path(), "version");
+        assert!(matches!(e.kind(), ManifestErrorKind::Version(_)));
+
+        let e = error(r#"{ "engines": { "node": ">= 1.2..3" } }"#);
+        assert_eq!(e.path(), "engines.node");
+        assert!(matches!(e.kind(), ManifestErrorKind::Range(_)));
+
+        let e = error(r#"{ "peerDependenciesMeta": { "react": { "optional": "yes" } } }"#);
+        assert_eq!(e.path(), "peerDependenciesMeta.react.optional");
+
+        let e = error(r#"{ "os": ["linux", 1] }"#);
+        assert_eq!(e.path(), "os[1]");
+
+        let e = error(r#"{ "overrides": { "foo": { "bar": [] } } }"#);
+        assert_eq!(e.path(), "overrides.foo.bar");
+
+        let e = error(r#"{ "bin": "./cli.js" }"#);
+        assert_eq!(e.path(), "bin");
+
+        let e = error("[]");
+        assert_eq!(e.path(), "");
+        assert_eq!(e.to_string(), "expected an object");
+
+        let e = error("{ \"name\": ");
+        assert!(matches!(e.kind(), ManifestErrorKind::Json(_)));
+    }
+}
@@ -0,0 +1,270 @@
+//! Registry metadata read from disk.
+//!
+//! A registry snapshot is a directory holding the packument of each package,
+//! the document the npm registry serves at `/<name>`, as `<name>.json`. Scoped
+//! packages live in a directory per scope: `@types/node.json`.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Display;
+use std::path::PathBuf;
+
+use serde_json::Value;
+
+use crate::manifest::{
+    as_object, as_string, join, ManifestError, ManifestErrorKind, PackageManifest,
+};
+use crate::spec::is_package_name;
+use crate::version::{semver::Version, ParseOptions};
+
+/// What the registry knows about a package: its dist-tags and the manifest
+/// of every version it published.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Packument {
+    /// The name of the package.
+    pub name: String,
+    /// The version each dist-tag, such as `latest`, points at.
+    pub dist_tags: BTreeMap<String, Version>,
+    // The manifest of each version as published, under the key it was
+    // published with. They are only parsed once asked for, so an old version
+    // with a broken manifest doesn't keep the others from being installed.
+    versions: BTreeMap<Version, (String, Value)>,
+}
+
+// Versions published to the registry are valid in node-semver's loose mode.
+const LOOSE: ParseOptions = ParseOptions { loose: true };
+
+impl Packument {
+    /// Parses a packument from its JSON text.
+    pub fn parse(input: &str) -> Result<Self, ManifestError> {
+        let value = serde_json::from_str::<Value>(input)
+            .map_err(|e| ManifestError::new("", ManifestErrorKind::Json(e)))?;
+        Packument::from_json(&value)
+    }
+
+    /// Reads a packument out of a document that was already parsed.
+    pub fn from_json(value: &Value) -> Result<Self, ManifestError> {
+        let root = as_object(value, "")?;
+        let mut packument = Packument {
+            name: as_string(root.get("name").unwrap_or(&Value::Null), "name")?.to_owned(),
+            ..Default::default()
+        };
+
+        let version = |input: &str, path: String| {
+            Version::parse_with_options(input, &LOOSE)
+                .map_err(|e| ManifestError::new(path, ManifestErrorKind::Version(e)))
+        };
+
+        if let Some(tags) = root.get("dist-tags") {
+            for (tag, value) in as_object(tags, "dist-tags")? {
+                let path = join("dist-tags", tag);
+                let tagged = version(as_string(value, &path)?, path)?;
+                packument.dist_tags.insert(tag.clone(), tagged);
+            }
+        }
+
+        if let Some(versions) = root.get("versions") {
+            for (key, manifest) in as_object(versions, "versions")? {
+                let path = join("versions", key);
+                packument
+                    .versions
+                    .insert(version(key, path)?, (key.clone(), manifest.clone()));
+            }
+        }
+
+        Ok(packument)
+    }
+
+    /// The published versions from the lowest to the highest.
+    pub fn versions(&self) -> impl DoubleEndedIterator<Item = &Version> {
+        self.versions.keys()
+    }
+
+    /// Parses the manifest of a version, `None` when it was never published.
+    pub fn manifest(&self, version: &Version) -> Option<Result<PackageManifest, ManifestError>> {
+        let (key, manifest) = self.versions.get(version)?;
+        Some(PackageManifest::from_json(manifest).map_err(|e| e.within(&join("versions", key))))
+    }
+
+    /// The version `latest` points at, which npm prefers whenever it fits.
+    pub fn latest(&self) -> Option<&Version> {
+        self.dist_tags.get("latest")
+    }
+}
+
+/// A registry snapshot on disk.
+#[derive(Debug)]
+pub struct Registry {
+    directory: PathBuf,
+    packuments: HashMap<String, Packument>,
+}
+
+impl Registry {
+    /// Opens the snapshot in `directory`. Nothing is read until a package is
+    /// asked for.
+    pub fn open(directory: impl Into<PathBuf>) -> Self {
+        Registry {
+            directory: directory.into(),
+            packuments: HashMap::new(),
+        }
+    }
+
+    /// Reads a packument the first time it is asked for and keeps it around.
+    pub fn packument(&mut self, name: &str) -> Result<&Packument, RegistryError> {
+        if !self.packuments.contains_key(name) {
+            let packument = self.read(name)?;
+            self.packuments.insert(name.to_owned(), packument);
+        }
+        Ok(&self.packuments[name])
+    }
+
+    fn read(&self, name: &str) -> Result<Packument, RegistryError> {
+        let error = |kind| RegistryError::new(name, kind);
+
+        // The name becomes a path, so anything that could leave the directory
+        // is turned away along with the other invalid names.
+        if !is_package_name(name) {
+            return Err(error(RegistryErrorKind::NotFound));
+        }
+
+        let path = self.directory.join(format!("{}.json", name));
+        let input = std::fs::read_to_string(path).map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => error(RegistryErrorKind::NotFound),
+            _ => error(RegistryErrorKind::Io(e)),
+        })?;
+
+        Packument::parse(&input).map_err(|e| error(RegistryErrorKind::Packument(Box::new(e))))
+    }
+}
+
+/// Why a packument could not be read.
+#[derive(Debug)]
+pub enum RegistryErrorKind {
+    /// The snapshot has no such package.
+    NotFound,
+    /// The packument is there but could not be read.
+    Io(std::io::Error),
+    /// The packument, or the manifest of the version that was picked, is
+    /// invalid.
+    Packument(Box<ManifestError>),
+}
+
+/// An error reading a package from a [`Registry`].
+#[derive(Debug)]
+pub struct RegistryError {
+    name: String,
+    kind: RegistryErrorKind,
+}
+
+impl RegistryError {
+    pub(crate) fn new(name: &str, kind: RegistryErrorKind) -> Self {
+        RegistryError {
+            name: name.to_owned(),
+            kind,
+        }
+    }
+
+    /// The package that was asked for.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// What went wrong.
+    pub fn kind(&self) -> &RegistryErrorKind {
+        &self.kind
+    }
+}
+
+impl Display for RegistryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.kind {
+            RegistryErrorKind::NotFound => {
+                write!(f, "package `{}` is not in the registry", self.name)
+            }
+            RegistryErrorKind::Io(e) => write!(f, "cannot read package `{}`: {}", self.name, e),
+            RegistryErrorKind::Packument(e) => {
+                write!(f, "invalid metadata for package `{}`: {}", self.name, e)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RegistryError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.kind {
+            RegistryErrorKind::NotFound => None,
+            RegistryErrorKind::Io(e) => Some(e),
+            RegistryErrorKind::Packument(e) => Some(e.as_ref()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packument() {
+        let packument = Packument::parse(
+            r#"{
+                "name": "a",
+                "dist-tags": { "latest": "1.1.0", "next": "2.0.0-rc.1" },
+                "versions": {
+                    "1.1.0": { "name": "a", "version": "1.1.0", "dependencies": { "b": "^1" } },
+                    "2.0.0-rc.1": { "name": "a", "version": "2.0.0-rc.1" }
+                }
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(packument.name, "a");
+        assert_eq!(packument.latest(), Some(&Version::parse("1.1.0").unwrap()));
+        assert_eq!(packument.versions().count(), 2);
+        let manifest = packument.manifest(&Version::parse("1.1.0").unwrap());
+        assert!(manifest.unwrap().unwrap().dependencies.contains_key("b"));
+        assert!(packument
+            .manifest(&Version::parse("1.0.0").unwrap())
+            .is_none());
+
+        let error = Packument::parse(r#"{ "name": "a", "versions": { "x": {} } }"#).unwrap_err();
+        assert_eq!(error.path(), "versions.x");
+    }
+
+    #[test]
+    fn broken_manifests() {
+        let mut registry = Registry::open(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/registry"
+        ));
+        let packument = registry.packument("broken").unwrap();
+
+        let valid = packument.manifest(&Version::parse("1.0.0").unwrap());
+        assert_eq!(
+            valid.unwrap().unwrap().version,
+            Version::parse("1.0.0").ok()
+        );
+
+        let error = packument
+            .manifest(&Version::parse("0.1.0").unwrap())
+            .unwrap()
+            .unwrap_err();
+        assert_eq!(error.path(), r#"versions["0.1.0"].dependencies.y"#);
+    }
+
+    #[test]
+    fn missing_packages() {
+        let mut registry = Registry::open(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/registry"
+        ));
+
+        assert_eq!(registry.packument("shared").unwrap().name, "shared");
+        for name in ["nothing-here", "../Cargo", "@scope/../x"] {
+            let error = registry.packument(name).unwrap_err();
+            assert!(
+                matches!(error.kind(), RegistryErrorKind::NotFound),
+                "{}",
+                name
+            );
+        }
+    }
+}
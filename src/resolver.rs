@@ -0,0 +1,680 @@
+//! Resolving a project's dependencies against a registry snapshot.
+//!
+//! Packages are laid out the way npm lays out `node_modules`: breadth first,
+//! each one as close to the root as it can go without hiding another version
+//! from a package that already uses it, and not at all when a version that
+//! fits is already visible from the package that needs it.
+
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::fmt::Display;
+
+use crate::graph::{DependencyGraph, DependencyKind, NodeId};
+use crate::manifest::PackageManifest;
+use crate::registry::{Registry, RegistryError, RegistryErrorKind};
+use crate::spec::DependencySpec;
+use crate::version::{condition::Condition, semver::Version};
+
+/// The packages a project resolves to and where they are installed.
+#[derive(Clone, Debug)]
+pub struct Resolution {
+    /// Every package installed, and which ones each depends on.
+    pub graph: DependencyGraph,
+    /// The node of the project itself.
+    pub root: NodeId,
+    /// Where each package is installed, keyed like the `packages` of a
+    /// package-lock: `""` for the root, then `node_modules/a`,
+    /// `node_modules/a/node_modules/b` and so on.
+    pub layout: BTreeMap<String, NodeId>,
+}
+
+/// Resolves the dependencies of the `root` manifest, its dev dependencies
+/// included, and those of everything they pull in.
+pub fn resolve(
+    root: &PackageManifest,
+    registry: &mut Registry,
+) -> Result<Resolution, ResolveError> {
+    let mut graph = DependencyGraph::new();
+    let root_id = graph.add_package(
+        root.name.as_deref().unwrap_or(""),
+        root.version.clone().unwrap_or_default(),
+    );
+
+    let mut resolver = Resolver {
+        registry,
+        graph,
+        locations: vec![Location {
+            node: root_id,
+            name: String::new(),
+            parent: None,
+            children: BTreeMap::new(),
+        }],
+        resolutions: vec![],
+        edges: HashSet::new(),
+    };
+
+    let mut queue = VecDeque::from([(0, dependencies(root, true))]);
+    while let Some((location, dependencies)) = queue.pop_front() {
+        for dependency in dependencies {
+            match resolver.resolve(location, &dependency) {
+                Ok(Some(placed)) => queue.push_back(placed),
+                Ok(None) => (),
+                // npm carries on without optional dependencies it can't get.
+                Err(_) if dependency.optional => (),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    let mut layout = BTreeMap::new();
+    for (i, location) in resolver.locations.iter().enumerate() {
+        layout.insert(resolver.path(i), location.node);
+    }
+
+    Ok(Resolution {
+        graph: resolver.graph,
+        root: root_id,
+        layout,
+    })
+}
+
+struct Dependency {
+    name: String,
+    spec: DependencySpec,
+    kind: DependencyKind,
+    optional: bool,
+}
+
+// What a package asks to be installed with it, sorted by name as npm does.
+// Dev dependencies only count for the root, and a dependency that is also
+// optional is only optional.
+fn dependencies(manifest: &PackageManifest, root: bool) -> Vec<Dependency> {
+    let mut dependencies = BTreeMap::new();
+    let mut add = |map: &BTreeMap<String, DependencySpec>, kind| {
+        for (name, spec) in map {
+            let optional = kind == DependencyKind::Optional
+                || kind == DependencyKind::Peer
+                    && manifest
+                        .peer_dependencies_meta
+                        .get(name)
+                        .is_some_and(|m| m.optional);
+            dependencies.insert(
+                name.clone(),
+                Dependency {
+                    name: name.clone(),
+                    spec: spec.clone(),
+                    kind,
+                    optional,
+                },
+            );
+        }
+    };
+
+    add(&manifest.peer_dependencies, DependencyKind::Peer);
+    if root {
+        add(&manifest.dev_dependencies, DependencyKind::Dev);
+    }
+    add(&manifest.dependencies, DependencyKind::Prod);
+    add(&manifest.optional_dependencies, DependencyKind::Optional);
+
+    dependencies.into_values().collect()
+}
+
+// A package installed in the `node_modules` of its parent.
+struct Location {
+    node: NodeId,
+    name: String,
+    parent: Option<usize>,
+    children: BTreeMap<String, usize>,
+}
+
+// The version a dependency asks for, once aliases are seen through.
+enum Selector<'d> {
+    Range(&'d Condition),
+    Tag(&'d str),
+}
+
+struct Resolver<'r> {
+    registry: &'r mut Registry,
+    graph: DependencyGraph,
+    locations: Vec<Location>,
+    // Which location each dependency was found at, by the location that
+    // depends on it and the name it is installed under.
+    resolutions: Vec<(usize, String, usize)>,
+    // The edges already in the graph. Copies of a package installed in
+    // different places can find different versions of the same dependency,
+    // and each of those gets an edge.
+    edges: HashSet<(NodeId, String, NodeId)>,
+}
+
+impl Resolver<'_> {
+    // Finds or places the package a dependency asks for. A package placed
+    // somewhere new comes back with its own dependencies to resolve there.
+    fn resolve(
+        &mut self,
+        from: usize,
+        dependency: &Dependency,
+    ) -> Result<Option<(usize, Vec<Dependency>)>, ResolveError> {
+        let error = |resolver: &Self, kind| ResolveError {
+            name: dependency.name.clone(),
+            spec: Box::new(dependency.spec.clone()),
+            path: resolver.path(from),
+            kind,
+        };
+
+        let (package, selector) = match &dependency.spec {
+            DependencySpec::Range(range) => (dependency.name.as_str(), Selector::Range(range)),
+            DependencySpec::Tag(tag) => (dependency.name.as_str(), Selector::Tag(tag)),
+            DependencySpec::Alias { name, spec } => match spec.as_ref() {
+                DependencySpec::Range(range) => (name.as_str(), Selector::Range(range)),
+                DependencySpec::Tag(tag) => (name.as_str(), Selector::Tag(tag)),
+                _ => return Err(error(self, ResolveErrorKind::UnsupportedSpec)),
+            },
+            _ => return Err(error(self, ResolveErrorKind::UnsupportedSpec)),
+        };
+
+        // Peers are installed next to the package that wants them.
+        let start = match dependency.kind {
+            DependencyKind::Peer => self.locations[from].parent.unwrap_or(from),
+            _ => from,
+        };
+
+        // Walk up the way Node looks for a package: the first copy found is
+        // the one that would be used, and above it nothing can be placed.
+        let mut candidates = vec![];
+        let mut at = Some(start);
+        while let Some(location) = at {
+            if let Some(&existing) = self.locations[location].children.get(&dependency.name) {
+                let installed = self.graph.package(self.locations[existing].node).clone();
+                let selected = installed.name == package
+                    && self
+                        .selects(&selector, package, &installed.version)
+                        .map_err(|e| error(self, ResolveErrorKind::Registry(e)))?;
+                if selected {
+                    self.link(from, dependency, existing, &selector);
+                    return Ok(None);
+                }
+                break;
+            }
+            candidates.push(location);
+            at = self.locations[location].parent;
+        }
+
+        if dependency.kind == DependencyKind::Peer && dependency.optional {
+            return Ok(None);
+        }
+
+        let Some((version, dependencies)) = self
+            .select(package, &selector)
+            .map_err(|e| error(self, ResolveErrorKind::Registry(e)))?
+        else {
+            return Err(error(self, ResolveErrorKind::NoMatchingVersion));
+        };
+
+        let Some(target) = candidates
+            .iter()
+            .rev()
+            .copied()
+            .find(|&target| !self.shadows(target, &dependency.name))
+        else {
+            return Err(error(self, ResolveErrorKind::Conflict));
+        };
+
+        // A copy the walk went past is hidden by a nearer one, so a package
+        // gets nested inside a copy of itself when something in between
+        // installs another version under its name. A copy nested in two of
+        // them is a cycle, `a@1` wanting `a@2` wanting `a@1`, with no end.
+        if self.copies(target, &dependency.name, package, &version) > 1 {
+            return Err(error(self, ResolveErrorKind::Conflict));
+        }
+
+        let node = self.graph.add_package(package, version);
+        let placed = self.locations.len();
+        self.locations.push(Location {
+            node,
+            name: dependency.name.clone(),
+            parent: Some(target),
+            children: BTreeMap::new(),
+        });
+        self.locations[target]
+            .children
+            .insert(dependency.name.clone(), placed);
+        self.link(from, dependency, placed, &selector);
+
+        Ok(Some((placed, dependencies)))
+    }
+
+    // npm takes the version `latest` points at when it fits the range, and
+    // the highest version that does otherwise.
+    fn select(
+        &mut self,
+        package: &str,
+        selector: &Selector,
+    ) -> Result<Option<(Version, Vec<Dependency>)>, RegistryError> {
+        let packument = self.registry.packument(package)?;
+        let version = match selector {
+            Selector::Tag(tag) => packument.dist_tags.get(*tag),
+            Selector::Range(range) => packument
+                .latest()
+                .filter(|latest| range.compare(latest))
+                .or_else(|| packument.versions().rev().find(|v| range.compare(v))),
+        };
+        let Some(version) = version else {
+            return Ok(None);
+        };
+        match packument.manifest(version) {
+            Some(Ok(manifest)) => Ok(Some((version.clone(), dependencies(&manifest, false)))),
+            Some(Err(e)) => Err(RegistryError::new(
+                package,
+                RegistryErrorKind::Packument(Box::new(e)),
+            )),
+            None => Ok(None),
+        }
+    }
+
+    fn selects(
+        &mut self,
+        selector: &Selector,
+        package: &str,
+        version: &Version,
+    ) -> Result<bool, RegistryError> {
+        Ok(match selector {
+            Selector::Range(range) => range.compare(version),
+            Selector::Tag(tag) => {
+                self.registry.packument(package)?.dist_tags.get(*tag) == Some(version)
+            }
+        })
+    }
+
+    // Whether a package placed at `target` under `name` would hide the copy
+    // that a package below `target` already found further up.
+    fn shadows(&self, target: usize, name: &str) -> bool {
+        self.resolutions.iter().any(|(from, n, to)| {
+            n == name
+                && self.is_within(*from, target)
+                && self.locations[*to]
+                    .parent
+                    .is_some_and(|parent| parent != target && !self.is_within(parent, target))
+        })
+    }
+
+    // How many copies of a package, under `name`, `location` is installed in.
+    fn copies(&self, location: usize, name: &str, package: &str, version: &Version) -> usize {
+        let mut copies = 0;
+        let mut at = Some(location);
+        while let Some(location) = at {
+            let installed = self.graph.package(self.locations[location].node);
+            if self.locations[location].name == name
+                && installed.name == package
+                && installed.version == *version
+            {
+                copies += 1;
+            }
+            at = self.locations[location].parent;
+        }
+        copies
+    }
+
+    fn is_within(&self, location: usize, ancestor: usize) -> bool {
+        let mut at = Some(location);
+        while let Some(location) = at {
+            if location == ancestor {
+                return true;
+            }
+            at = self.locations[location].parent;
+        }
+        false
+    }
+
+    fn link(&mut self, from: usize, dependency: &Dependency, to: usize, selector: &Selector) {
+        self.resolutions.push((from, dependency.name.clone(), to));
+
+        let from = self.locations[from].node;
+        let to = self.locations[to].node;
+        if !self.edges.insert((from, dependency.name.clone(), to)) {
+            return;
+        }
+        // A tag stands for the exact version it pointed at.
+        let condition = match selector {
+            Selector::Range(range) => (*range).clone(),
+            Selector::Tag(_) => Condition::Simple(self.graph.package(to).version.clone()),
+        };
+        self.graph
+            .add_dependency(from, to, &dependency.name, dependency.kind, condition);
+    }
+
+    fn path(&self, location: usize) -> String {
+        let mut names = vec![];
+        let mut at = location;
+        while let Some(parent) = self.locations[at].parent {
+            names.push(self.locations[at].name.as_str());
+            at = parent;
+        }
+        names
+            .iter()
+            .rev()
+            .map(|name| format!("node_modules/{}", name))
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+}
+
+/// Why a dependency could not be resolved.
+#[derive(Debug)]
+pub enum ResolveErrorKind {
+    /// The package could not be read from the registry.
+    Registry(RegistryError),
+    /// No published version fits the spec.
+    NoMatchingVersion,
+    /// Only registry specs can be resolved against a registry snapshot.
+    UnsupportedSpec,
+    /// Every place the package could go would hide another version of it
+    /// from a package that uses that one.
+    Conflict,
+}
+
+/// An error resolving one dependency.
+#[derive(Debug)]
+pub struct ResolveError {
+    name: String,
+    spec: Box<DependencySpec>,
+    // The location of the package that has the dependency.
+    path: String,
+    kind: ResolveErrorKind,
+}
+
+impl ResolveError {
+    /// The name the dependency is declared under.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The spec it is declared with.
+    pub fn spec(&self) -> &DependencySpec {
+        &self.spec
+    }
+
+    /// Where the package that has the dependency is installed, empty for the
+    /// root.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// What went wrong.
+    pub fn kind(&self) -> &ResolveErrorKind {
+        &self.kind
+    }
+}
+
+impl Display for ResolveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let ResolveErrorKind::Registry(e) = &self.kind {
+            return write!(f, "{}", e);
+        }
+
+        let location = if self.path.is_empty() {
+            "the root"
+        } else {
+            &self.path
+        };
+        write!(
+            f,
+            "cannot resolve `{}@{}` for {}: ",
+            self.name, self.spec, location
+        )?;
+        match &self.kind {
+            ResolveErrorKind::Registry(_) => unreachable!(),
+            ResolveErrorKind::NoMatchingVersion => write!(f, "no version matches"),
+            ResolveErrorKind::UnsupportedSpec => write!(f, "not a registry dependency"),
+            ResolveErrorKind::Conflict => write!(f, "it conflicts with an installed version"),
+        }
+    }
+}
+
+impl std::error::Error for ResolveError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.kind {
+            ResolveErrorKind::Registry(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry(name: &str) -> Registry {
+        Registry::open(format!(
+            "{}/tests/fixtures/{}",
+            env!("CARGO_MANIFEST_DIR"),
+            name
+        ))
+    }
+
+    fn manifest(input: &str) -> PackageManifest {
+        PackageManifest::parse(input).unwrap()
+    }
+
+    fn installed(resolution: &Resolution) -> Vec<(&str, String)> {
+        resolution
+            .layout
+            .iter()
+            .map(|(path, &id)| (path.as_str(), resolution.graph[id].to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn hoisting() {
+        let root = manifest(
+            r#"{
+                "name": "project",
+                "version": "1.0.0",
+                "dependencies": {
+                    "a": "^1",
+                    "a2": "npm:a@^2",
+                    "b": "^1",
+                    "tagged": "next",
+                    "@scope/util": "^1"
+                },
+                "devDependencies": { "c": "^2" },
+                "optionalDependencies": { "missing": "^1" }
+            }"#,
+        );
+        let resolution = resolve(&root, &mut registry("registry")).unwrap();
+
+        assert_eq!(
+            installed(&resolution),
+            [
+                ("", "project@1.0.0".to_owned()),
+                ("node_modules/@scope/util", "@scope/util@1.0.0".to_owned()),
+                ("node_modules/a", "a@1.1.0".to_owned()),
+                ("node_modules/a2", "a@2.0.0".to_owned()),
+                ("node_modules/b", "b@1.0.0".to_owned()),
+                (
+                    "node_modules/b/node_modules/shared",
+                    "shared@2.0.0".to_owned()
+                ),
+                ("node_modules/c", "c@2.0.0".to_owned()),
+                ("node_modules/peer", "peer@1.3.0".to_owned()),
+                ("node_modules/shared", "shared@1.2.0".to_owned()),
+                ("node_modules/tagged", "tagged@2.0.0-rc.1".to_owned()),
+            ]
+        );
+
+        let graph = &resolution.graph;
+        assert_eq!(graph.package_count(), 10);
+        assert_eq!(graph.versions("a").count(), 2);
+
+        let root_edges = graph
+            .dependencies_of(resolution.root)
+            .map(|(_, d)| (d.name.as_str(), d.kind, graph[d.to].to_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            root_edges,
+            [
+                (
+                    "@scope/util",
+                    DependencyKind::Prod,
+                    "@scope/util@1.0.0".to_owned()
+                ),
+                ("a", DependencyKind::Prod, "a@1.1.0".to_owned()),
+                ("a2", DependencyKind::Prod, "a@2.0.0".to_owned()),
+                ("b", DependencyKind::Prod, "b@1.0.0".to_owned()),
+                ("c", DependencyKind::Dev, "c@2.0.0".to_owned()),
+                (
+                    "tagged",
+                    DependencyKind::Prod,
+                    "tagged@2.0.0-rc.1".to_owned()
+                ),
+            ]
+        );
+
+        let tagged = resolution.layout["node_modules/tagged"];
+        let (_, edge) = graph.dependents_of(tagged).next().unwrap();
+        assert_eq!(
+            edge.condition,
+            Condition::Simple(Version::parse("2.0.0-rc.1").unwrap())
+        );
+
+        // `c` shares the hoisted `shared` with `a`.
+        let shared = resolution.layout["node_modules/shared"];
+        let dependents = graph
+            .dependents_of(shared)
+            .map(|(_, d)| graph[d.from].to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(dependents, ["a@1.1.0", "c@2.0.0"]);
+
+        let a = resolution.layout["node_modules/a"];
+        let (_, peer) = graph
+            .dependencies_of(a)
+            .find(|(_, d)| d.name == "peer")
+            .unwrap();
+        assert_eq!(peer.kind, DependencyKind::Peer);
+    }
+
+    #[test]
+    fn shadowing() {
+        let root =
+            manifest(r#"{ "dependencies": { "a": "^1", "b": "^1", "d": "^2", "e": "^2" } }"#);
+        let resolution = resolve(&root, &mut registry("shadowing")).unwrap();
+
+        // `b@2` can't go in `a`'s `node_modules`, where it would hide `b@1`
+        // from `a/node_modules/d`.
+        assert_eq!(
+            installed(&resolution),
+            [
+                ("", "@0.0.0".to_owned()),
+                ("node_modules/a", "a@1.0.0".to_owned()),
+                ("node_modules/a/node_modules/d", "d@1.0.0".to_owned()),
+                ("node_modules/a/node_modules/e", "e@1.0.0".to_owned()),
+                (
+                    "node_modules/a/node_modules/e/node_modules/b",
+                    "b@2.0.0".to_owned()
+                ),
+                ("node_modules/b", "b@1.0.0".to_owned()),
+                ("node_modules/d", "d@2.0.0".to_owned()),
+                ("node_modules/e", "e@2.0.0".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn cycles() {
+        // `a@2` finds itself in `a@1`, so each version needs the other nested
+        // inside it, over and over.
+        let root = manifest(r#"{ "dependencies": { "a": "^1" } }"#);
+        let e = resolve(&root, &mut registry("cycle")).unwrap_err();
+        assert!(matches!(e.kind(), ResolveErrorKind::Conflict));
+        assert_eq!(e.name(), "a");
+
+        // `y` in `a@1` finds the `a@2` nested next to it, not the `a@1` it is
+        // installed in, and gets a copy of its own.
+        let root = manifest(r#"{ "dependencies": { "a": "^1", "y": "^2" } }"#);
+        let resolution = resolve(&root, &mut registry("nesting")).unwrap();
+        assert_eq!(
+            installed(&resolution),
+            [
+                ("", "@0.0.0".to_owned()),
+                ("node_modules/a", "a@1.0.0".to_owned()),
+                ("node_modules/a/node_modules/a", "a@2.0.0".to_owned()),
+                ("node_modules/a/node_modules/y", "y@1.0.0".to_owned()),
+                (
+                    "node_modules/a/node_modules/y/node_modules/a",
+                    "a@1.0.0".to_owned()
+                ),
+                (
+                    "node_modules/a/node_modules/y/node_modules/a/node_modules/a",
+                    "a@2.0.0".to_owned()
+                ),
+                ("node_modules/y", "y@2.0.0".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn duplicates() {
+        let root =
+            manifest(r#"{ "dependencies": { "a": "^1", "b": "^1", "p": "^2", "y": "^1" } }"#);
+        let resolution = resolve(&root, &mut registry("duplicates")).unwrap();
+
+        assert_eq!(
+            installed(&resolution),
+            [
+                ("", "@0.0.0".to_owned()),
+                ("node_modules/a", "a@1.0.0".to_owned()),
+                ("node_modules/a/node_modules/p", "p@1.0.0".to_owned()),
+                ("node_modules/a/node_modules/y", "y@2.0.0".to_owned()),
+                ("node_modules/b", "b@1.0.0".to_owned()),
+                ("node_modules/b/node_modules/p", "p@1.0.0".to_owned()),
+                ("node_modules/p", "p@2.0.0".to_owned()),
+                ("node_modules/y", "y@1.0.0".to_owned()),
+            ]
+        );
+
+        // Both copies of `p@1` share a node, which keeps the `y` each found.
+        let graph = &resolution.graph;
+        let p = resolution.layout["node_modules/a/node_modules/p"];
+        assert_eq!(resolution.layout["node_modules/b/node_modules/p"], p);
+        let found = graph
+            .dependencies_of(p)
+            .map(|(_, d)| (d.name.as_str(), graph[d.to].to_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            [("y", "y@2.0.0".to_owned()), ("y", "y@1.0.0".to_owned())]
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let error = |input: &str| resolve(&manifest(input), &mut registry("registry")).unwrap_err();
+
+        let e = error(r#"{ "dependencies": { "shared": "^9" } }"#);
+        assert!(matches!(e.kind(), ResolveErrorKind::NoMatchingVersion));
+        assert_eq!(
+            e.to_string(),
+            "cannot resolve `shared@^9` for the root: no version matches"
+        );
+
+        let e = error(r#"{ "dependencies": { "a": "github:user/a" } }"#);
+        assert!(matches!(e.kind(), ResolveErrorKind::UnsupportedSpec));
+
+        let e = error(r#"{ "dependencies": { "c": "^2", "missing": "^1" } }"#);
+        assert!(matches!(e.kind(), ResolveErrorKind::Registry(_)));
+        assert_eq!(e.name(), "missing");
+        assert_eq!(e.to_string(), "package `missing` is not in the registry");
+
+        // Only the version that is picked has to have a valid manifest.
+        let root = manifest(r#"{ "dependencies": { "broken": "^1" } }"#);
+        let resolution = resolve(&root, &mut registry("registry")).unwrap();
+        assert!(resolution.layout.contains_key("node_modules/broken"));
+
+        let e = error(r#"{ "dependencies": { "broken": "^0.1" } }"#);
+        assert!(matches!(e.kind(), ResolveErrorKind::Registry(_)));
+        assert_eq!(
+            e.to_string(),
+            "invalid metadata for package `broken`: `versions[\"0.1.0\"].dependencies.y`: \
+             invalid dependency spec `this is not a spec`: not a range, a location or a valid tag"
+        );
+    }
+}
@@ -0,0 +1,582 @@
+//! What a dependency asks for, the value side of `"name": "<spec>"`.
+//!
+//! Specs are read the way npm-package-arg reads them: a range or a dist-tag of
+//! a registry package, an alias to another registry package, a git repository,
+//! a tarball URL or a local path. Displaying a spec gives a string that parses
+//! back to the same spec.
+
+use std::fmt::Display;
+
+use crate::version::{condition::Condition, ParseError, ParseOptions};
+
+/// What a dependency is declared with in a manifest: a range or tag on the
+/// registry, or somewhere else to get the package from.
+#[derive(Clone, Debug, PartialEq)]
+// Ranges are by far the most common specs, so they aren't boxed to make the
+// other forms smaller.
+#[allow(clippy::large_enum_variant)]
+pub enum DependencySpec {
+    /// A range of versions on the registry, `^1.2.3`.
+    Range(Condition),
+    /// A dist-tag on the registry, `latest`.
+    Tag(String),
+    /// `npm:name@spec`, installs the registry package `name` under another name.
+    Alias {
+        /// The package that is installed.
+        name: String,
+        /// The range or tag it is installed with.
+        spec: Box<DependencySpec>,
+    },
+    /// A git repository, `github:user/repo#v1.0.0`.
+    Git {
+        /// Where the repository is.
+        repository: GitRepository,
+        /// What to check out, the default branch when `None`.
+        reference: Option<GitReference>,
+    },
+    /// The URL of a tarball.
+    Tarball(String),
+    /// A directory or a tarball on disk, `file:` is optional for paths that
+    /// start like one, such as `../pkg`.
+    File(String),
+    /// `link:<path>`, a directory on disk that is symlinked.
+    Link(String),
+    /// `workspace:`, a package of the same workspace.
+    Workspace(WorkspaceRange),
+}
+
+/// Where a git dependency is cloned from.
+#[derive(Clone, Debug, PartialEq)]
+pub enum GitRepository {
+    /// `github:user/repo`, or `user/repo` for short.
+    Hosted {
+        /// The host the repository is on.
+        host: GitHost,
+        /// The `user/repo` part.
+        path: String,
+    },
+    /// Any other git URL, scp-like ones such as `git@host:user/repo.git` included.
+    Url(String),
+}
+
+/// The hosts that git specs have a shorthand for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GitHost {
+    /// `github:`.
+    GitHub,
+    /// `gitlab:`.
+    GitLab,
+    /// `bitbucket:`.
+    Bitbucket,
+    /// `gist:`.
+    Gist,
+}
+
+impl GitHost {
+    const ALL: [GitHost; 4] = [
+        GitHost::GitHub,
+        GitHost::GitLab,
+        GitHost::Bitbucket,
+        GitHost::Gist,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            GitHost::GitHub => "github",
+            GitHost::GitLab => "gitlab",
+            GitHost::Bitbucket => "bitbucket",
+            GitHost::Gist => "gist",
+        }
+    }
+}
+
+/// What follows the `#` of a git spec: a branch, tag or commit, or
+/// `semver:<range>` to pick the highest matching tag.
+#[derive(Clone, Debug, PartialEq)]
+pub enum GitReference {
+    /// A branch, a tag or a commit.
+    Committish(String),
+    /// `semver:<range>`.
+    Semver(Box<Condition>),
+}
+
+/// `workspace:^` and `workspace:~` stand for the workspace's own version with
+/// that operator in front of it.
+#[derive(Clone, Debug, PartialEq)]
+pub enum WorkspaceRange {
+    /// `workspace:^`.
+    Caret,
+    /// `workspace:~`.
+    Tilde,
+    /// Any other range, `workspace:*` included.
+    Range(Box<Condition>),
+}
+
+// npm reads ranges with node-semver's loose mode.
+const LOOSE: ParseOptions = ParseOptions { loose: true };
+
+impl DependencySpec {
+    /// Parses a spec the way npm reads the values of `dependencies`.
+    pub fn parse(input: &str) -> Result<Self, SpecError> {
+        let error = |kind| SpecError {
+            kind,
+            input: input.to_owned(),
+        };
+
+        // npm reads an empty spec as `*`.
+        if input.trim().is_empty() {
+            return Ok(DependencySpec::Range(Condition::Any));
+        }
+
+        if let Some(alias) = input.strip_prefix("npm:") {
+            let (name, spec) = split_alias(alias);
+            if !is_package_name(name) {
+                return Err(error(SpecErrorKind::InvalidName));
+            }
+            let spec = match spec {
+                Some(spec) => DependencySpec::parse(spec).map_err(|e| error(e.kind))?,
+                None => DependencySpec::Range(Condition::Any),
+            };
+            if !matches!(spec, DependencySpec::Range(_) | DependencySpec::Tag(_)) {
+                return Err(error(SpecErrorKind::InvalidAlias));
+            }
+            return Ok(DependencySpec::Alias {
+                name: name.to_owned(),
+                spec: Box::new(spec),
+            });
+        }
+
+        if let Some(range) = input.strip_prefix("workspace:") {
+            let range = match range {
+                "^" => WorkspaceRange::Caret,
+                "~" => WorkspaceRange::Tilde,
+                range => WorkspaceRange::Range(Box::new(
+                    parse_range(range).map_err(|e| error(SpecErrorKind::Range(e)))?,
+                )),
+            };
+            return Ok(DependencySpec::Workspace(range));
+        }
+
+        for (prefix, variant) in [
+            (
+                "file:",
+                DependencySpec::File as fn(String) -> DependencySpec,
+            ),
+            ("link:", DependencySpec::Link),
+        ] {
+            if let Some(path) = input.strip_prefix(prefix) {
+                if path.is_empty() {
+                    return Err(error(SpecErrorKind::Empty));
+                }
+                return Ok(variant(path.to_owned()));
+            }
+        }
+
+        if let Some((repository, reference)) = git_repository(input) {
+            let reference = match reference {
+                None => None,
+                Some("") => return Err(error(SpecErrorKind::Empty)),
+                Some(reference) => Some(match reference.strip_prefix("semver:") {
+                    Some(range) => GitReference::Semver(Box::new(
+                        parse_range(range).map_err(|e| error(SpecErrorKind::Range(e)))?,
+                    )),
+                    None => GitReference::Committish(reference.to_owned()),
+                }),
+            };
+            return Ok(DependencySpec::Git {
+                repository,
+                reference,
+            });
+        }
+
+        if input.starts_with("http://") || input.starts_with("https://") {
+            return Ok(DependencySpec::Tarball(input.to_owned()));
+        }
+
+        if let Ok(range) = Condition::parse_with_options(input, &LOOSE) {
+            return Ok(DependencySpec::Range(range));
+        }
+
+        if is_path(input) {
+            return Ok(DependencySpec::File(input.to_owned()));
+        }
+
+        if is_tag(input) {
+            return Ok(DependencySpec::Tag(input.to_owned()));
+        }
+
+        Err(error(SpecErrorKind::InvalidTag))
+    }
+
+    /// Whether the spec is served by the registry, by its own name or an alias.
+    pub fn is_registry(&self) -> bool {
+        matches!(
+            self,
+            DependencySpec::Range(_) | DependencySpec::Tag(_) | DependencySpec::Alias { .. }
+        )
+    }
+}
+
+impl Display for DependencySpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DependencySpec::Range(range) => write!(f, "{}", range),
+            DependencySpec::Tag(tag) => write!(f, "{}", tag),
+            DependencySpec::Alias { name, spec } => write!(f, "npm:{}@{}", name, spec),
+            DependencySpec::Git {
+                repository,
+                reference,
+            } => {
+                match repository {
+                    GitRepository::Hosted { host, path } => write!(f, "{}:{}", host.name(), path)?,
+                    GitRepository::Url(url) => write!(f, "{}", url)?,
+                }
+                match reference {
+                    Some(GitReference::Committish(committish)) => write!(f, "#{}", committish),
+                    Some(GitReference::Semver(range)) => write!(f, "#semver:{}", range),
+                    None => Ok(()),
+                }
+            }
+            DependencySpec::Tarball(url) => write!(f, "{}", url),
+            DependencySpec::File(path) => write!(f, "file:{}", path),
+            DependencySpec::Link(path) => write!(f, "link:{}", path),
+            DependencySpec::Workspace(WorkspaceRange::Caret) => write!(f, "workspace:^"),
+            DependencySpec::Workspace(WorkspaceRange::Tilde) => write!(f, "workspace:~"),
+            DependencySpec::Workspace(WorkspaceRange::Range(range)) => {
+                write!(f, "workspace:{}", range)
+            }
+        }
+    }
+}
+
+fn parse_range(input: &str) -> Result<Condition, ParseError> {
+    if input.trim().is_empty() {
+        return Ok(Condition::Any);
+    }
+    Condition::parse_with_options(input, &LOOSE)
+}
+
+// The `@` of a scoped name isn't the one that starts the spec.
+fn split_alias(alias: &str) -> (&str, Option<&str>) {
+    match alias.get(1..).and_then(|rest| rest.find('@')) {
+        Some(at) => (&alias[..at + 1], Some(&alias[at + 2..])),
+        None => (alias, None),
+    }
+}
+
+pub(crate) fn is_package_name(name: &str) -> bool {
+    let valid = |part: &str| {
+        !part.is_empty()
+            && !part.starts_with('.')
+            && !part.starts_with('_')
+            && part
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b"-._~".contains(&b))
+    };
+    match name.strip_prefix('@') {
+        Some(scoped) => scoped
+            .split_once('/')
+            .is_some_and(|(scope, name)| valid(scope) && valid(name)),
+        None => valid(name),
+    }
+}
+
+// Splits a git spec into its repository and what follows the `#`. Git URLs
+// are spelled with a `git` protocol, `git+<protocol>`, or scp-like as in
+// `git@github.com:user/repo.git`; plain http URLs count when they end in `.git`.
+fn git_repository(input: &str) -> Option<(GitRepository, Option<&str>)> {
+    let (repository, reference) = match input.split_once('#') {
+        Some((repository, reference)) => (repository, Some(reference)),
+        None => (input, None),
+    };
+
+    for host in GitHost::ALL {
+        let hosted = repository
+            .strip_prefix(host.name())
+            .and_then(|rest| rest.strip_prefix(':'));
+        if let Some(path) = hosted {
+            let path = path.to_owned();
+            return Some((GitRepository::Hosted { host, path }, reference));
+        }
+    }
+
+    let is_url = repository.starts_with("git+")
+        || repository.starts_with("git://")
+        || is_scp_like(repository)
+        || ((repository.starts_with("http://") || repository.starts_with("https://"))
+            && repository.ends_with(".git"));
+    if is_url {
+        return Some((GitRepository::Url(repository.to_owned()), reference));
+    }
+
+    // `user/repo` is short for a GitHub repository.
+    let shorthand = repository.split_once('/').is_some_and(|(user, project)| {
+        let valid = |part: &str| {
+            !part.is_empty()
+                && part
+                    .bytes()
+                    .all(|b| b.is_ascii_alphanumeric() || b"-._".contains(&b))
+        };
+        valid(user) && !user.starts_with('.') && !user.starts_with('@') && valid(project)
+    });
+    if shorthand {
+        let path = repository.to_owned();
+        return Some((
+            GitRepository::Hosted {
+                host: GitHost::GitHub,
+                path,
+            },
+            reference,
+        ));
+    }
+
+    None
+}
+
+fn is_scp_like(input: &str) -> bool {
+    input
+        .split_once('@')
+        .and_then(|(user, rest)| Some((user, rest.split_once(':')?)))
+        .is_some_and(|(user, (host, path))| {
+            !user.is_empty()
+                && !user.contains(['/', ':'])
+                && host.contains('.')
+                && !host.contains('/')
+                && !path.is_empty()
+        })
+}
+
+fn is_path(input: &str) -> bool {
+    input.starts_with('.')
+        || input.starts_with('/')
+        || input.starts_with("~/")
+        || [".tgz", ".tar.gz", ".tar"]
+            .iter()
+            .any(|extension| input.ends_with(extension))
+}
+
+// Tags are what `encodeURIComponent` leaves untouched.
+fn is_tag(input: &str) -> bool {
+    input
+        .bytes()
+        .all(|b| b.is_ascii_alphanumeric() || b"-_.!~*'()".contains(&b))
+}
+
+/// Why a spec is invalid.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SpecErrorKind {
+    /// Neither a range nor anything else, and not usable as a dist-tag.
+    InvalidTag,
+    /// The package an alias points at has an invalid name.
+    InvalidName,
+    /// Aliases only point at registry packages.
+    InvalidAlias,
+    /// A range that looked like one but is invalid.
+    Range(ParseError),
+    /// Nothing after `file:`, `link:` or the `#` of a git spec.
+    Empty,
+}
+
+/// An invalid dependency spec.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpecError {
+    kind: SpecErrorKind,
+    input: String,
+}
+
+impl SpecError {
+    /// What is wrong with it.
+    pub fn kind(&self) -> &SpecErrorKind {
+        &self.kind
+    }
+
+    /// The spec as it was written.
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+}
+
+impl Display for SpecError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid dependency spec `{}`: ", self.input)?;
+        match &self.kind {
+            SpecErrorKind::InvalidTag => write!(f, "not a range, a location or a valid tag"),
+            SpecErrorKind::InvalidName => write!(f, "invalid package name"),
+            SpecErrorKind::InvalidAlias => write!(f, "aliases must point at a registry package"),
+            SpecErrorKind::Range(e) => write!(f, "{}", e),
+            SpecErrorKind::Empty => write!(f, "missing a path or reference"),
+        }
+    }
+}
+
+impl std::error::Error for SpecError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.kind {
+            SpecErrorKind::Range(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> DependencySpec {
+        DependencySpec::parse(input).unwrap()
+    }
+
+    fn range(input: &str) -> Condition {
+        Condition::parse(input).unwrap()
+    }
+
+    #[test]
+    fn registry() {
+        assert_eq!(parse("^1.2.3"), DependencySpec::Range(range("^1.2.3")));
+        assert_eq!(parse(""), DependencySpec::Range(Condition::Any));
+        assert_eq!(parse("v1.x"), DependencySpec::Range(range("1.x")));
+        assert_eq!(parse("latest"), DependencySpec::Tag("latest".to_owned()));
+        assert_eq!(parse("next-11"), DependencySpec::Tag("next-11".to_owned()));
+    }
+
+    #[test]
+    fn aliases() {
+        assert_eq!(
+            parse("npm:lodash@^4"),
+            DependencySpec::Alias {
+                name: "lodash".to_owned(),
+                spec: Box::new(DependencySpec::Range(range("^4"))),
+            }
+        );
+        assert_eq!(
+            parse("npm:@types/node@latest"),
+            DependencySpec::Alias {
+                name: "@types/node".to_owned(),
+                spec: Box::new(DependencySpec::Tag("latest".to_owned())),
+            }
+        );
+        assert_eq!(
+            parse("npm:@scope/pkg"),
+            DependencySpec::Alias {
+                name: "@scope/pkg".to_owned(),
+                spec: Box::new(DependencySpec::Range(Condition::Any)),
+            }
+        );
+    }
+
+    #[test]
+    fn git() {
+        assert_eq!(
+            parse("github:user/repo#v1.2.3"),
+            DependencySpec::Git {
+                repository: GitRepository::Hosted {
+                    host: GitHost::GitHub,
+                    path: "user/repo".to_owned(),
+                },
+                reference: Some(GitReference::Committish("v1.2.3".to_owned())),
+            }
+        );
+        assert_eq!(parse("user/repo"), parse("github:user/repo"));
+        assert_eq!(
+            parse("git+ssh://git@github.com/user/repo.git#semver:^2"),
+            DependencySpec::Git {
+                repository: GitRepository::Url("git+ssh://git@github.com/user/repo.git".to_owned()),
+                reference: Some(GitReference::Semver(Box::new(range("^2")))),
+            }
+        );
+        assert_eq!(
+            parse("git@gitlab.com:user/repo.git"),
+            DependencySpec::Git {
+                repository: GitRepository::Url("git@gitlab.com:user/repo.git".to_owned()),
+                reference: None,
+            }
+        );
+        assert_eq!(
+            parse("https://github.com/user/repo.git"),
+            DependencySpec::Git {
+                repository: GitRepository::Url("https://github.com/user/repo.git".to_owned()),
+                reference: None,
+            }
+        );
+    }
+
+    #[test]
+    fn locations() {
+        assert_eq!(
+            parse("https://registry.example.com/x/-/x-1.0.0.tgz"),
+            DependencySpec::Tarball("https://registry.example.com/x/-/x-1.0.0.tgz".to_owned())
+        );
+        assert_eq!(
+            parse("file:../pkg"),
+            DependencySpec::File("../pkg".to_owned())
+        );
+        assert_eq!(parse("../pkg"), DependencySpec::File("../pkg".to_owned()));
+        assert_eq!(parse("pkg.tgz"), DependencySpec::File("pkg.tgz".to_owned()));
+        assert_eq!(
+            parse("link:../pkg"),
+            DependencySpec::Link("../pkg".to_owned())
+        );
+        assert_eq!(
+            parse("workspace:^"),
+            DependencySpec::Workspace(WorkspaceRange::Caret)
+        );
+        assert_eq!(
+            parse("workspace:*"),
+            DependencySpec::Workspace(WorkspaceRange::Range(Box::new(Condition::Any)))
+        );
+        assert_eq!(
+            parse("workspace:^1.2.0"),
+            DependencySpec::Workspace(WorkspaceRange::Range(Box::new(range("^1.2.0"))))
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        for input in [
+            "^1.2.3",
+            ">=1.0.0 <2.0.0 || 3.x",
+            "latest",
+            "npm:lodash@^4",
+            "npm:@types/node@next",
+            "npm:lodash",
+            "github:user/repo#v1.2.3",
+            "user/repo",
+            "gist:11081aaa281",
+            "git+ssh://git@github.com/user/repo.git#semver:^2",
+            "git@gitlab.com:user/repo.git#main",
+            "https://example.com/x-1.0.0.tgz",
+            "file:../pkg",
+            "./pkg",
+            "link:../pkg",
+            "workspace:^",
+            "workspace:~",
+            "workspace:*",
+            "workspace:^1.2.0",
+        ] {
+            let spec = parse(input);
+            assert_eq!(parse(&spec.to_string()), spec, "{}", input);
+        }
+    }
+
+    #[test]
+    fn errors() {
+        let kind = |input: &str| DependencySpec::parse(input).unwrap_err().kind().clone();
+
+        assert_eq!(kind("not a tag"), SpecErrorKind::InvalidTag);
+        assert_eq!(kind("npm:Bad Name@1"), SpecErrorKind::InvalidName);
+        assert_eq!(kind("npm:foo@file:../foo"), SpecErrorKind::InvalidAlias);
+        assert_eq!(kind("file:"), SpecErrorKind::Empty);
+        assert_eq!(kind("github:user/repo#"), SpecErrorKind::Empty);
+        assert!(matches!(kind("workspace:^1..2"), SpecErrorKind::Range(_)));
+        assert!(matches!(
+            kind("user/repo#semver:>>1"),
+            SpecErrorKind::Range(_)
+        ));
+
+        let error = DependencySpec::parse("not a tag").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid dependency spec `not a tag`: not a range, a location or a valid tag"
+        );
+    }
+}
@@ -1,14 +1,23 @@
+//! Version ranges: parsing, matching versions against them and reasoning
+//! about how ranges relate to each other.
+
 use super::{
-    semver::Version,
-    token::{tokenize, Token},
-    ParseError,
+    interval::{self, successor, Cut, Interval, Kind, Piece},
+    semver::{PartialVersion, Version},
+    token::{tokenize, trim_spaces, Token, TokenKind},
+    ErrorKind, Expected, ParseError, ParseOptions, Span,
 };
 
+/// A single comparator against a version.
 #[derive(Clone, Debug, PartialEq)]
 pub enum ConditionRange {
+    /// `<`.
     Less(Version),
+    /// `<=`.
     LessEqual(Version),
+    /// `>`.
     Greater(Version),
+    /// `>=`.
     GreaterEqual(Version),
 }
 
@@ -24,13 +33,93 @@ impl std::fmt::Display for ConditionRange {
     }
 }
 
+impl ConditionRange {
+    /// The version compared against.
+    pub fn version(&self) -> &Version {
+        match self {
+            ConditionRange::Less(v)
+            | ConditionRange::LessEqual(v)
+            | ConditionRange::Greater(v)
+            | ConditionRange::GreaterEqual(v) => v,
+        }
+    }
+
+    /// Whether a version passes the comparator, by precedence alone.
+    pub fn compare(&self, version: &Version) -> bool {
+        match self {
+            ConditionRange::Less(v) => version < v,
+            ConditionRange::LessEqual(v) => version <= v,
+            ConditionRange::Greater(v) => version > v,
+            ConditionRange::GreaterEqual(v) => version >= v,
+        }
+    }
+}
+
+/// The operator of a comparator on a partial version.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operator {
+    /// `<`.
+    Less,
+    /// `<=`.
+    LessEqual,
+    /// `>`.
+    Greater,
+    /// `>=`.
+    GreaterEqual,
+}
+
+impl std::fmt::Display for Operator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let operator = match self {
+            Operator::Less => "<",
+            Operator::LessEqual => "<=",
+            Operator::Greater => ">",
+            Operator::GreaterEqual => ">=",
+        };
+        write!(f, "{}", operator)
+    }
+}
+
+/// Options for matching versions against a condition.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CompareOptions {
+    /// Let pre-releases match any condition their version fits, like
+    /// node-semver's `includePrerelease`.
+    pub include_prerelease: bool,
+}
+
+/// Which side of a condition a version is on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Side {
+    /// Higher than every version that satisfies it.
+    Above,
+    /// Lower than every version that satisfies it.
+    Below,
+}
+
+/// A range of versions, as written in a `package.json`.
 #[derive(Clone, Debug, PartialEq)]
 pub enum Condition {
+    /// `*`, or an empty range.
     Any,
+    /// An exact version, `1.2.3`.
     Simple(Version),
-    Compatible(Version),
-    CompatibleWithMostRecent(Version),
-    Range(ConditionRange, Option<ConditionRange>),
+    /// A version with wildcards or missing components, `1.2.x` or `1`.
+    Partial(PartialVersion),
+    /// A tilde range, `~1.2.3`.
+    Compatible(PartialVersion),
+    /// A caret range, `^1.2.3`.
+    CompatibleWithMostRecent(PartialVersion),
+    /// Comparators that must all pass, `>=1.2.3 <2.0.0`.
+    Range(Vec<ConditionRange>),
+    /// A comparator on a partial version, `>=1.2` or `<=1`, which stands for
+    /// every version sharing the components that were written.
+    PartialRange(Operator, PartialVersion),
+    /// Conditions that must all pass, `^1.2.3 <1.5.0`.
+    Intersection(Vec<Condition>),
+    /// An inclusive range, `1.2.3 - 2.3.4`.
+    HyphenRange(PartialVersion, PartialVersion),
+    /// Alternatives, `^1.2.3 || ^2.0.0`.
     Composite(Vec<Condition>),
 }
 
@@ -39,16 +128,21 @@ impl std::fmt::Display for Condition {
         let condition = match self {
             Condition::Any => "*".to_owned(),
             Condition::Simple(version) => version.to_string(),
+            Condition::Partial(version) => version.to_string(),
             Condition::Compatible(version) => format!("~{version}").to_string(),
             Condition::CompatibleWithMostRecent(version) => format!("^{version}").to_string(),
-            Condition::Range(v1, v2) => format!(
-                "{v1}{}",
-                if v2.is_some() {
-                    format!(" {}", v2.clone().unwrap().to_string())
-                } else {
-                    "".to_owned()
-                }
-            ),
+            Condition::Range(ranges) => ranges
+                .iter()
+                .map(|r| r.to_string())
+                .collect::<Vec<String>>()
+                .join(" "),
+            Condition::PartialRange(operator, version) => format!("{operator}{version}"),
+            Condition::Intersection(conditions) => conditions
+                .iter()
+                .map(|c| c.to_string())
+                .collect::<Vec<String>>()
+                .join(" "),
+            Condition::HyphenRange(from, to) => format!("{from} - {to}"),
             Condition::Composite(versions) => versions
                 .iter()
                 .map(|v| v.to_string())
@@ -61,152 +155,775 @@ impl std::fmt::Display for Condition {
 }
 
 impl Condition {
+    /// Parses a range.
     pub fn parse(input: &str) -> Result<Self, ParseError> {
-        let input = input.trim();
+        Condition::parse_with_options(input, &ParseOptions::default())
+    }
 
-        if input.len() == 0 {
-            return Err(ParseError::EmptyInput);
+    /// Parses a range, loosely when `options` say so.
+    pub fn parse_with_options(input: &str, options: &ParseOptions) -> Result<Self, ParseError> {
+        if input.trim().is_empty() {
+            return Err(ParseError::empty_input(input));
         }
 
         let tokens = tokenize(input)?;
-        build_from_tokens(&tokens)
+        build_from_tokens(input, &tokens, options).map_err(|e| e.with_input(input))
     }
 
+    /// Whether a version satisfies the condition.
     pub fn compare(&self, version: &Version) -> bool {
+        self.compare_with_options(version, &CompareOptions::default())
+    }
+
+    /// Whether a version satisfies the condition under `options`.
+    pub fn compare_with_options(&self, version: &Version, options: &CompareOptions) -> bool {
+        self.to_comparator_sets_with_options(options)
+            .iter()
+            .any(|set| set_compare(set, version, options))
+    }
+
+    /// Lowers the condition into a union of comparator sets, each one being the
+    /// intersection of its primitive comparators. `*` is a single empty set.
+    pub fn to_comparator_sets(&self) -> Vec<ComparatorSet> {
+        self.to_comparator_sets_with_options(&CompareOptions::default())
+    }
+
+    /// Lowers the condition for matching under `options`. With
+    /// `include_prerelease`, ranges built from a partial version start at its
+    /// lowest pre-release, so `1.x` is `>=1.0.0-0 <2.0.0-0`.
+    pub fn to_comparator_sets_with_options(&self, options: &CompareOptions) -> Vec<ComparatorSet> {
         match self {
-            Condition::Any => true,
-            Condition::Simple(v) => v == version,
-            Condition::Compatible(v) => {
-                v.major == version.major && v.minor == version.minor && v.patch <= version.patch
-            }
-            Condition::CompatibleWithMostRecent(v) => {
-                !(v.major != version.major
-                    || v.minor > version.minor
-                    || (v.minor == version.minor && v.patch > version.patch))
-            }
+            Condition::Composite(conditions) => conditions
+                .iter()
+                .flat_map(|c| c.to_comparator_sets_with_options(options))
+                .collect(),
+            _ => vec![self.comparators(options)],
+        }
+    }
 
-            Condition::Range(left, right) => {
-                let version = version.get_version_offset();
+    /// The versions that satisfy the condition, in the order given.
+    pub fn filter_satisfying<'a>(
+        &self,
+        versions: &'a [Version],
+    ) -> impl Iterator<Item = &'a Version> + use<'a> {
+        let sets = self.to_comparator_sets();
+        let options = CompareOptions::default();
+        versions
+            .iter()
+            .filter(move |v| sets.iter().any(|set| set_compare(set, v, &options)))
+    }
 
-                let left_offset = match left {
-                    ConditionRange::Greater(v) => v.get_version_offset() < version,
-                    ConditionRange::GreaterEqual(v) => v.get_version_offset() <= version,
-                    _ => unreachable!("NEVER BEGINS WITH LESS"),
+    /// The highest version that satisfies the condition.
+    pub fn max_satisfying<'a>(&self, versions: &'a [Version]) -> Option<&'a Version> {
+        self.filter_satisfying(versions).max()
+    }
+
+    /// The lowest version that satisfies the condition.
+    pub fn min_satisfying<'a>(&self, versions: &'a [Version]) -> Option<&'a Version> {
+        self.filter_satisfying(versions).min()
+    }
+
+    /// `sorted` must be in ascending order. Each comparator set is narrowed down
+    /// with a binary search, so only versions within its bounds are visited.
+    pub fn max_satisfying_sorted<'a>(&self, sorted: &'a [Version]) -> Option<&'a Version> {
+        let options = CompareOptions::default();
+        self.to_comparator_sets()
+            .iter()
+            .filter_map(|set| {
+                let (low, high) = sorted_bounds(set, sorted);
+                sorted[low..high]
+                    .iter()
+                    .rev()
+                    .find(|v| set_compare(set, v, &options))
+            })
+            .max()
+    }
+
+    /// Like [`Condition::max_satisfying_sorted`], for the lowest version.
+    pub fn min_satisfying_sorted<'a>(&self, sorted: &'a [Version]) -> Option<&'a Version> {
+        let options = CompareOptions::default();
+        self.to_comparator_sets()
+            .iter()
+            .filter_map(|set| {
+                let (low, high) = sorted_bounds(set, sorted);
+                sorted[low..high]
+                    .iter()
+                    .find(|v| set_compare(set, v, &options))
+            })
+            .min()
+    }
+
+    /// Whether a version is higher than every version the condition allows.
+    pub fn is_above(&self, version: &Version) -> bool {
+        self.outside(version) == Some(Side::Above)
+    }
+
+    /// Whether a version is lower than every version the condition allows.
+    pub fn is_below(&self, version: &Version) -> bool {
+        self.outside(version) == Some(Side::Below)
+    }
+
+    /// Which side of the condition a version falls on when it is past the
+    /// bounds of every comparator set. Versions that fail the condition while
+    /// still sitting in between its bounds, such as an excluded pre-release or
+    /// one in the gap of `<1 || >=2`, are on neither side.
+    pub fn outside(&self, version: &Version) -> Option<Side> {
+        if self.compare(version) {
+            return None;
+        }
+
+        let intervals = self
+            .to_comparator_sets()
+            .iter()
+            .map(|set| Interval::from_comparators(set))
+            .filter(|interval| !interval.is_empty())
+            .collect::<Vec<_>>();
+        if intervals.is_empty() {
+            return None;
+        }
+
+        if intervals.iter().all(|i| !i.below_upper(version)) {
+            Some(Side::Above)
+        } else if intervals.iter().all(|i| !i.above_lower(version)) {
+            Some(Side::Below)
+        } else {
+            None
+        }
+    }
+
+    /// The lowest version that satisfies the condition, like node-semver's
+    /// `minVersion`.
+    pub fn min_version(&self) -> Option<Version> {
+        self.pieces()
+            .iter()
+            .filter_map(|piece| {
+                let candidate = match &piece.interval.lower {
+                    Cut::Before(v) => v.clone(),
+                    Cut::After(v) => {
+                        let mut v = v.clone();
+                        v.pre_release.push("0");
+                        v
+                    }
+                    Cut::Start | Cut::End => return None,
                 };
+                piece.interval.contains(&candidate).then_some(candidate)
+            })
+            .min()
+    }
 
-                if !left_offset {
-                    return false;
+    /// The ceiling no satisfying version can exceed, `None` when there is none.
+    pub fn upper_bound(&self) -> Option<ConditionRange> {
+        let upper = self
+            .to_comparator_sets()
+            .iter()
+            .map(|set| Interval::from_comparators(set))
+            .filter(|interval| !interval.is_empty())
+            .map(|interval| interval.upper)
+            .fold(Cut::Start, Cut::max);
+
+        match upper {
+            Cut::Start => Some(ConditionRange::Less(lowest_pre_release(0, 0, 0))),
+            Cut::Before(v) => Some(ConditionRange::Less(v)),
+            Cut::After(v) => Some(ConditionRange::LessEqual(v)),
+            Cut::End => None,
+        }
+    }
+
+    /// The versions both conditions allow, `None` when there are none.
+    pub fn intersect(&self, other: &Condition) -> Option<Condition> {
+        let written = [self, other]
+            .iter()
+            .flat_map(|c| c.to_comparator_sets())
+            .flatten()
+            .collect::<ComparatorSet>();
+
+        let mut conditions = render_pieces(
+            interval::intersect(&self.pieces(), &other.pieces()),
+            &written,
+        );
+        match conditions.len() {
+            0 => None,
+            1 => conditions.pop(),
+            _ => Some(Condition::Composite(conditions)),
+        }
+    }
+
+    /// Whether some version satisfies both conditions.
+    pub fn intersects(&self, other: &Condition) -> bool {
+        !interval::intersect(&self.pieces(), &other.pieces()).is_empty()
+    }
+
+    /// Whether every version this condition allows is allowed by `other` too.
+    pub fn is_subset_of(&self, other: &Condition) -> bool {
+        let other = other.pieces();
+        self.pieces().iter().all(|p| interval::covers(&other, p))
+    }
+
+    /// Merges overlapping and adjacent alternatives into the fewest intervals,
+    /// keeping the original condition when that reads shorter.
+    pub fn simplify(&self) -> Condition {
+        let simplified = match render_pieces(interval::union(self.pieces()), &[]) {
+            conditions if conditions.is_empty() => return self.clone(),
+            mut conditions if conditions.len() == 1 => conditions.pop().unwrap(),
+            conditions => Condition::Composite(conditions),
+        };
+
+        shortest(self, simplified)
+    }
+
+    /// Like `semver.simplifyRange`: describes the given versions that satisfy
+    /// the condition with as few comparators as possible.
+    pub fn simplify_with_versions(&self, versions: &[Version]) -> Condition {
+        let mut versions = versions.iter().collect::<Vec<&Version>>();
+        versions.sort();
+
+        let mut runs: Vec<(&Version, Option<&Version>)> = vec![];
+        let mut run: Option<(&Version, &Version)> = None;
+        for version in versions.iter().copied() {
+            match (self.compare(version), run) {
+                (true, Some((first, _))) => run = Some((first, version)),
+                (true, None) => run = Some((version, version)),
+                (false, Some((first, last))) => {
+                    runs.push((first, Some(last)));
+                    run = None;
                 }
+                (false, None) => (),
+            }
+        }
+        if let Some((first, _)) = run {
+            runs.push((first, None));
+        }
 
-                match right {
-                    Some(right) => match right {
-                        ConditionRange::Less(v) => v.get_version_offset() > version,
-                        ConditionRange::LessEqual(v) => v.get_version_offset() >= version,
-                        _ => unreachable!("NEVER BEGINS WITH LESS"),
-                    },
-                    None => true,
+        let mut conditions = runs
+            .into_iter()
+            .map(|(first, last)| match last {
+                Some(last) if first == last => Condition::Simple(first.clone()),
+                None if first == versions[0] => Condition::Any,
+                None => Condition::Range(vec![ConditionRange::GreaterEqual(first.clone())]),
+                Some(last) if first == versions[0] => {
+                    Condition::Range(vec![ConditionRange::LessEqual(last.clone())])
                 }
+                Some(last) => Condition::HyphenRange(first.clone().into(), last.clone().into()),
+            })
+            .collect::<Vec<Condition>>();
+
+        let simplified = match conditions.len() {
+            0 => return self.clone(),
+            1 => conditions.pop().unwrap(),
+            _ => Condition::Composite(conditions),
+        };
+
+        shortest(self, simplified)
+    }
+
+    fn pieces(&self) -> Vec<Piece> {
+        self.to_comparator_sets()
+            .iter()
+            .flat_map(|set| interval::pieces(set))
+            .collect()
+    }
+
+    fn comparators(&self, options: &CompareOptions) -> ComparatorSet {
+        match self {
+            Condition::Any => vec![],
+            Condition::Simple(v) => vec![
+                ConditionRange::GreaterEqual(v.clone()),
+                ConditionRange::LessEqual(v.clone()),
+            ],
+            Condition::Partial(v) => {
+                std::iter::once(ConditionRange::GreaterEqual(floor(v.to_version(), options)))
+                    .chain(partial_ceiling(v))
+                    .collect()
+            }
+            Condition::Compatible(v) => tilde_bounds(v),
+            Condition::CompatibleWithMostRecent(v) => caret_bounds(v, options),
+            Condition::Range(ranges) => ranges.clone(),
+            Condition::PartialRange(operator, version) => {
+                partial_comparator(*operator, version, options)
+                    .into_iter()
+                    .collect()
             }
-            Condition::Composite(conditions) => {
-                conditions.iter().find(|c| c.compare(version)).is_some()
+            Condition::Intersection(conditions) => conditions
+                .iter()
+                .flat_map(|c| c.comparators(options))
+                .collect(),
+            Condition::HyphenRange(from, to) => {
+                let (left, right) = hyphen_bounds(from, to, options);
+                left.into_iter().chain(right).collect()
+            }
+            Condition::Composite(_) => unreachable!("composites span several comparator sets"),
+        }
+    }
+}
+
+/// Comparators that a version must all pass.
+pub type ComparatorSet = Vec<ConditionRange>;
+
+fn sorted_bounds(set: &[ConditionRange], sorted: &[Version]) -> (usize, usize) {
+    debug_assert!(sorted.windows(2).all(|w| w[0] <= w[1]));
+
+    let interval = Interval::from_comparators(set);
+    let low = sorted.partition_point(|v| !interval.above_lower(v));
+    let high = sorted.partition_point(|v| interval.below_upper(v));
+
+    (low, high.max(low))
+}
+
+fn shortest(original: &Condition, simplified: Condition) -> Condition {
+    if simplified.to_string().len() < original.to_string().len() {
+        simplified
+    } else {
+        original.clone()
+    }
+}
+
+// Renders merged pieces, folding the pre-releases that lead straight into a
+// release interval back into it, e.g. `>=1.2.3-beta <1.2.3` and
+// `>=1.2.3 <2.0.0` read as `^1.2.3-beta`.
+fn render_pieces(pieces: Vec<Piece>, written: &[ConditionRange]) -> Vec<Condition> {
+    let (mut releases, pre_releases): (Vec<Piece>, Vec<Piece>) =
+        pieces.into_iter().partition(|p| p.kind == Kind::Release);
+
+    let mut remaining = vec![];
+    for piece in pre_releases {
+        let Kind::PreRelease(major, minor, patch) = piece.kind else {
+            continue;
+        };
+        let tuple = Cut::Before(Version {
+            major,
+            minor,
+            patch,
+            ..Default::default()
+        });
+        let release = releases
+            .iter_mut()
+            .find(|r| r.interval.lower == tuple && piece.interval.upper == tuple);
+
+        match release {
+            Some(release) => release.interval.lower = piece.interval.lower,
+            None => remaining.push(piece),
+        }
+    }
+
+    releases.extend(remaining);
+    releases.sort_by(|l, r| l.interval.lower.cmp(&r.interval.lower));
+    releases.iter().map(|p| render_piece(p, written)).collect()
+}
+
+fn render_piece(piece: &Piece, written: &[ConditionRange]) -> Condition {
+    let Interval {
+        lower: Cut::Before(lower),
+        upper: Cut::Before(upper),
+    } = &piece.interval
+    else {
+        return match written_bounds(piece, written) {
+            comparators if comparators.is_empty() => Condition::Any,
+            comparators => Condition::Range(comparators),
+        };
+    };
+
+    let tuple = (lower.major, lower.minor, lower.patch);
+    let ceiling = (upper.major, upper.minor, upper.patch);
+    let caret = match tuple {
+        (0, 0, patch) => successor(&[0, 0, patch]),
+        (0, minor, _) => successor(&[0, minor]),
+        (major, _, _) => successor(&[major]),
+    };
+
+    if piece.kind != Kind::Release || !upper.pre_release.is_empty() {
+        Condition::Range(piece.to_comparators())
+    } else if lower.pre_release.is_empty()
+        && Some(ceiling) == successor(&[tuple.0, tuple.1, tuple.2])
+    {
+        Condition::Simple(lower.clone())
+    } else if Some(ceiling) == caret {
+        Condition::CompatibleWithMostRecent(lower.clone().into())
+    } else if Some(ceiling) == successor(&[tuple.0, tuple.1]) {
+        Condition::Compatible(lower.clone().into())
+    } else {
+        Condition::Range(written_bounds(piece, written))
+    }
+}
+
+// Release pieces keep their bounds as `>=` and `<`, which turns `<=1.3.0`
+// into `<1.3.1` and `>1.2.3` into `>=1.2.4`. Puts back the comparator that
+// was written when one of them is the same bound over releases.
+fn written_bounds(piece: &Piece, written: &[ConditionRange]) -> ComparatorSet {
+    let mut comparators = piece.to_comparators();
+    if piece.kind != Kind::Release {
+        return comparators;
+    }
+
+    for comparator in &mut comparators {
+        let original = written.iter().find(|w| match (&*comparator, w) {
+            (ConditionRange::Less(v), ConditionRange::LessEqual(u))
+            | (ConditionRange::GreaterEqual(v), ConditionRange::Greater(u)) => {
+                v.pre_release.is_empty()
+                    && u.pre_release.is_empty()
+                    && Some((v.major, v.minor, v.patch)) == successor(&[u.major, u.minor, u.patch])
             }
+            _ => false,
+        });
+        if let Some(original) = original {
+            *comparator = original.clone();
         }
     }
+
+    comparators
+}
+
+// A pre-release only satisfies a comparator set when one of its comparators
+// opts into pre-releases of that exact major.minor.patch tuple.
+fn set_compare(set: &[ConditionRange], version: &Version, options: &CompareOptions) -> bool {
+    if !set.iter().all(|c| c.compare(version)) {
+        return false;
+    }
+
+    version.pre_release.is_empty()
+        || options.include_prerelease
+        || set.iter().map(|c| c.version()).any(|v| {
+            !v.pre_release.is_empty()
+                && v.major == version.major
+                && v.minor == version.minor
+                && v.patch == version.patch
+        })
+}
+
+// npm's hyphen ranges are inclusive, but a partial upper bound covers every
+// version sharing the components that were written: `1.2 - 2.3` is
+// `>=1.2.0 <2.4.0-0`.
+fn hyphen_bounds(
+    from: &PartialVersion,
+    to: &PartialVersion,
+    options: &CompareOptions,
+) -> (Option<ConditionRange>, Option<ConditionRange>) {
+    let left = from
+        .major
+        .map(|_| ConditionRange::GreaterEqual(floor(from.to_version(), options)));
+    let right = match to.major {
+        None => None,
+        Some(_) if to.is_partial() => partial_ceiling(to),
+        Some(_) => Some(ConditionRange::LessEqual(to.to_version())),
+    };
+
+    (left, right)
+}
+
+// The exclusive ceiling of everything a partial version stands for: `1` is
+// `<2.0.0-0` and `1.2` is `<1.3.0-0`. There is none when no release follows.
+fn partial_ceiling(version: &PartialVersion) -> Option<ConditionRange> {
+    let major = version.major?;
+    let (major, minor, patch) = match (version.minor, version.patch) {
+        (None, _) => successor(&[major])?,
+        (Some(minor), None) => successor(&[major, minor])?,
+        _ => return None,
+    };
+
+    Some(ConditionRange::Less(lowest_pre_release(
+        major, minor, patch,
+    )))
+}
+
+// A tilde range allows patch-level changes when a minor version was written
+// and minor-level changes otherwise: `~1.2.3` and `~1.2` stop at `1.3.0`,
+// while `~1` stops at `2.0.0`.
+fn tilde_bounds(version: &PartialVersion) -> Vec<ConditionRange> {
+    let Some(major) = version.major else {
+        return vec![];
+    };
+
+    let ceiling = match version.minor {
+        None => successor(&[major]),
+        Some(minor) => successor(&[major, minor]),
+    };
+
+    std::iter::once(ConditionRange::GreaterEqual(version.to_version()))
+        .chain(ceiling.map(|(major, minor, patch)| {
+            ConditionRange::Less(lowest_pre_release(major, minor, patch))
+        }))
+        .collect()
+}
+
+// A caret range allows every change that keeps the left-most non-zero
+// component written: `^1.2.3` stops at `2.0.0`, `^0.2.3` at `0.3.0` and
+// `^0.0.3` at `0.0.4`.
+fn caret_bounds(version: &PartialVersion, options: &CompareOptions) -> Vec<ConditionRange> {
+    let Some(major) = version.major else {
+        return vec![];
+    };
+
+    let ceiling = match (major, version.minor, version.patch) {
+        (_, None, _) => successor(&[major]),
+        (0, Some(minor), None) => successor(&[0, minor]),
+        (0, Some(0), Some(patch)) => successor(&[0, 0, patch]),
+        (0, Some(minor), Some(_)) => successor(&[0, minor]),
+        _ => successor(&[major]),
+    };
+    let lower = if version.is_partial() {
+        floor(version.to_version(), options)
+    } else {
+        version.to_version()
+    };
+
+    std::iter::once(ConditionRange::GreaterEqual(lower))
+        .chain(ceiling.map(|(major, minor, patch)| {
+            ConditionRange::Less(lowest_pre_release(major, minor, patch))
+        }))
+        .collect()
+}
+
+// node-semver's `includePrerelease` moves the floor of a range built from a
+// partial version, or of a hyphen range, down to its lowest pre-release:
+// `>=1.2` becomes `>=1.2.0-0`.
+fn floor(version: Version, options: &CompareOptions) -> Version {
+    if options.include_prerelease && version.pre_release.is_empty() {
+        lowest_pre_release(version.major, version.minor, version.patch)
+    } else {
+        version
+    }
 }
 
-fn build_from_tokens(tokens: &[Token]) -> Result<Condition, ParseError> {
-    if tokens.len() == 0 {
-        return Err(ParseError::EmptyTokenList);
+fn lowest_pre_release(major: u64, minor: u64, patch: u64) -> Version {
+    Version {
+        major,
+        minor,
+        patch,
+        pre_release: ["0"].into_iter().collect(),
+        ..Default::default()
     }
+}
 
-    if tokens.contains(&Token::Or) {
-        let mut tokens = tokens;
+fn build_from_tokens(
+    input: &str,
+    tokens: &[Token],
+    options: &ParseOptions,
+) -> Result<Condition, ParseError> {
+    let tokens = trim_spaces(tokens);
 
-        let mut idx = tokens.iter().position(|t| t == &Token::Or);
+    if tokens.iter().any(|t| t.kind == TokenKind::Or) {
+        let mut rest = tokens;
         let mut conditions = vec![];
-        while idx.is_some() {
-            let condition = build_from_tokens(&tokens[..idx.unwrap()])?;
-            conditions.push(condition);
+        while let Some(idx) = rest.iter().position(|t| t.kind == TokenKind::Or) {
+            let alternative = trim_spaces(&rest[..idx]);
+            if alternative.is_empty() {
+                return Err(ParseError::new(ErrorKind::UnexpectedToken, rest[idx].span)
+                    .expecting(&[Expected::Version]));
+            }
+            conditions.push(build_from_tokens(input, alternative, options)?);
 
-            tokens = &tokens[idx.unwrap() + 1..];
-            idx = tokens.iter().position(|t| t == &Token::Or);
+            rest = &rest[idx + 1..];
         }
 
-        let condition = build_from_tokens(tokens)?;
-        conditions.push(condition);
+        if trim_spaces(rest).is_empty() {
+            let end = tokens.last().map_or(0, |t| t.span.end);
+            return Err(ParseError::new(ErrorKind::UnexpectedEnd, Span::at(end))
+                .expecting(&[Expected::Version]));
+        }
+        conditions.push(build_from_tokens(input, rest, options)?);
 
         return Ok(Condition::Composite(conditions));
     }
 
-    match &tokens[0] {
-        Token::Asterisk => Ok(Condition::Any),
-        Token::Caret => {
-            let version = super::semver::build_from_tokens(&tokens[1..])?;
-            Ok(Condition::CompatibleWithMostRecent(version))
-        }
-        Token::Tilde => {
-            let version = super::semver::build_from_tokens(&tokens[1..])?;
-            Ok(Condition::Compatible(version))
+    let hyphen = tokens.windows(3).position(|w| {
+        w[0].kind == TokenKind::Space
+            && w[1].kind == TokenKind::Hyphen
+            && w[2].kind == TokenKind::Space
+    });
+    if let Some(idx) = hyphen {
+        let from = super::semver::build_partial_from_tokens(input, &tokens[..idx], options)?;
+        let to = super::semver::build_partial_from_tokens(input, &tokens[idx + 3..], options)?;
+        return Ok(Condition::HyphenRange(from, to));
+    }
+
+    // Each comparator keeps the form it was written in. Wildcards add
+    // nothing to the set, and runs of plain comparators read as one range.
+    let mut conditions = vec![];
+    for comparator in split_comparators(tokens, options) {
+        match build_comparator_from_tokens(input, comparator, options)? {
+            Condition::Any => (),
+            Condition::Range(ranges) => match conditions.last_mut() {
+                Some(Condition::Range(last)) => last.extend(ranges),
+                _ => conditions.push(Condition::Range(ranges)),
+            },
+            condition => conditions.push(condition),
         }
-        Token::Greater => build_range_condition_from_tokens(tokens, Token::Greater),
-        Token::GreaterEqual => build_range_condition_from_tokens(tokens, Token::GreaterEqual),
-        _ => {
-            let version = super::semver::build_from_tokens(tokens)?;
-            Ok(Condition::Simple(version))
+    }
+
+    Ok(match conditions.len() {
+        0 => Condition::Any,
+        1 => conditions.pop().unwrap(),
+        _ => Condition::Intersection(conditions),
+    })
+}
+
+fn is_operator(token: &TokenKind) -> bool {
+    matches!(
+        token,
+        TokenKind::Greater
+            | TokenKind::GreaterEqual
+            | TokenKind::Less
+            | TokenKind::LessEqual
+            | TokenKind::Equal
+            | TokenKind::Tilde
+            | TokenKind::Caret
+    )
+}
+
+// Comparators are separated by whitespace, but an operator may be followed by
+// spaces before its version (`>= 1.2.3`). Loose parsing also starts a new
+// comparator at an operator glued to the previous version, `1.2.3<2`; strict
+// parsing leaves it in the version, where it is reported.
+fn split_comparators<'t, 'a>(
+    tokens: &'t [Token<'a>],
+    options: &ParseOptions,
+) -> Vec<&'t [Token<'a>]> {
+    let mut comparators = vec![];
+    let mut start = 0;
+    let mut pending_operator = false;
+
+    for (i, token) in tokens.iter().enumerate() {
+        match &token.kind {
+            TokenKind::Space if pending_operator => continue,
+            TokenKind::Space => {
+                comparators.push(&tokens[start..i]);
+                start = i + 1;
+            }
+            t if is_operator(t) && i > start && !pending_operator && options.loose => {
+                comparators.push(&tokens[start..i]);
+                start = i;
+            }
+            _ => (),
         }
+        pending_operator = is_operator(&token.kind);
     }
+    comparators.push(&tokens[start..]);
+
+    comparators
 }
 
-fn build_range_condition_from_tokens(
+fn build_comparator_from_tokens(
+    input: &str,
     tokens: &[Token],
-    left_token: Token,
+    options: &ParseOptions,
 ) -> Result<Condition, ParseError> {
-    let idx = tokens
-        .iter()
-        .position(|t| *t == Token::Less || *t == Token::LessEqual);
-    if idx.is_none() {
-        let v = super::semver::build_from_tokens(&tokens[1..])?;
-        return Ok(Condition::Range(
-            range_condition_from_token(left_token, v),
-            None,
-        ));
-    }
-
-    let idx = idx.unwrap();
-    let v1 = super::semver::build_from_tokens(&tokens[1..idx])?;
-    let left_condition = range_condition_from_token(left_token, v1);
-
-    let tokens = &tokens[idx..];
-    match &tokens[0] {
-        Token::Less => {
-            let v2 = super::semver::build_from_tokens(&tokens[1..])?;
-            Ok(Condition::Range(
-                left_condition,
-                Some(ConditionRange::Less(v2)),
-            ))
-        }
-        Token::LessEqual => {
-            let v2 = super::semver::build_from_tokens(&tokens[1..])?;
-            Ok(Condition::Range(
-                left_condition,
-                Some(ConditionRange::LessEqual(v2)),
-            ))
-        }
-        _ => return Err(ParseError::Unexpected),
+    let (operator, operand) = match tokens {
+        [first, rest @ ..] if is_operator(&first.kind) => (&first.kind, trim_spaces(rest)),
+        _ => (&TokenKind::Equal, tokens),
+    };
+    if operand.is_empty() {
+        let end = tokens.last().map_or(0, |t| t.span.end);
+        return Err(ParseError::new(ErrorKind::UnexpectedEnd, Span::at(end))
+            .expecting(&[Expected::Version]));
+    }
+
+    match operator {
+        TokenKind::Caret => {
+            let version = super::semver::build_partial_from_tokens(input, operand, options)?;
+            Ok(match version {
+                PartialVersion { major: None, .. } => Condition::Any,
+                v => Condition::CompatibleWithMostRecent(v),
+            })
+        }
+        TokenKind::Tilde => {
+            let version = super::semver::build_partial_from_tokens(input, operand, options)?;
+            Ok(match version {
+                PartialVersion { major: None, .. } => Condition::Any,
+                v => Condition::Compatible(v),
+            })
+        }
+        TokenKind::Equal => {
+            let version = super::semver::build_partial_from_tokens(input, operand, options)?;
+            Ok(match version {
+                PartialVersion { major: None, .. } => Condition::Any,
+                v if v.is_partial() => Condition::Partial(v),
+                v => Condition::Simple(v.to_version()),
+            })
+        }
+        _ => {
+            let version = super::semver::build_partial_from_tokens(input, operand, options)?;
+            let operator = match operator {
+                TokenKind::Greater => Operator::Greater,
+                TokenKind::GreaterEqual => Operator::GreaterEqual,
+                TokenKind::Less => Operator::Less,
+                _ => Operator::LessEqual,
+            };
+            Ok(match version {
+                PartialVersion { major: Some(_), .. } if version.is_partial() => {
+                    Condition::PartialRange(operator, version)
+                }
+                version => match partial_comparator(operator, &version, &Default::default()) {
+                    Some(range) => Condition::Range(vec![range]),
+                    None => Condition::Any,
+                },
+            })
+        }
     }
 }
 
-fn range_condition_from_token(token: Token, version: Version) -> ConditionRange {
-    match token {
-        Token::Greater => ConditionRange::Greater(version),
-        Token::GreaterEqual => ConditionRange::GreaterEqual(version),
-        _ => unreachable!("Should never get here!"),
+// npm reads a partial operand as the whole block of versions it stands for:
+// `>1.2` is `>=1.3.0`, `<=1.2` is `<1.3.0-0` and `<1.2` is `<1.2.0-0`.
+fn partial_comparator(
+    operator: Operator,
+    version: &PartialVersion,
+    options: &CompareOptions,
+) -> Option<ConditionRange> {
+    if !version.is_partial() {
+        let version = version.to_version();
+        return Some(match operator {
+            Operator::Greater => ConditionRange::Greater(version),
+            Operator::GreaterEqual => ConditionRange::GreaterEqual(version),
+            Operator::Less => ConditionRange::Less(version),
+            Operator::LessEqual => ConditionRange::LessEqual(version),
+        });
+    }
+
+    let Some(major) = version.major else {
+        return match operator {
+            Operator::Greater | Operator::Less => {
+                Some(ConditionRange::Less(lowest_pre_release(0, 0, 0)))
+            }
+            _ => None,
+        };
+    };
+
+    // Past the last release, `>` matches nothing and `<=` everything.
+    let next = match version.minor {
+        None => successor(&[major]),
+        Some(minor) => successor(&[major, minor]),
+    };
+    let lowest = (major, version.minor.unwrap_or_default(), 0);
+
+    Some(match (operator, next) {
+        (Operator::Greater, Some(next)) => {
+            ConditionRange::GreaterEqual(floor(release(next), options))
+        }
+        (Operator::Greater, None) => ConditionRange::Less(lowest_pre_release(0, 0, 0)),
+        (Operator::GreaterEqual, _) => {
+            ConditionRange::GreaterEqual(floor(release(lowest), options))
+        }
+        (Operator::Less, _) => {
+            ConditionRange::Less(lowest_pre_release(lowest.0, lowest.1, lowest.2))
+        }
+        (Operator::LessEqual, Some(next)) => {
+            ConditionRange::Less(lowest_pre_release(next.0, next.1, next.2))
+        }
+        (Operator::LessEqual, None) => return None,
+    })
+}
+
+fn release((major, minor, patch): (u64, u64, u64)) -> Version {
+    Version {
+        major,
+        minor,
+        patch,
+        ..Default::default()
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::version::semver::Identifiers;
 
     #[test]
     fn basic_cases() {
@@ -230,9 +947,9 @@ mod test {
         let cond = Condition::parse(cond).unwrap();
         assert_eq!(
             cond,
-            Condition::Compatible(Version {
-                major: 2,
-                minor: 3,
+            Condition::Compatible(PartialVersion {
+                major: Some(2),
+                minor: Some(3),
                 ..Default::default()
             }),
         );
@@ -241,10 +958,10 @@ mod test {
         let cond = Condition::parse(cond).unwrap();
         assert_eq!(
             cond,
-            Condition::CompatibleWithMostRecent(Version {
-                major: 52,
-                minor: 13,
-                patch: 194,
+            Condition::CompatibleWithMostRecent(PartialVersion {
+                major: Some(52),
+                minor: Some(13),
+                patch: Some(194),
                 ..Default::default()
             }),
         );
@@ -256,98 +973,92 @@ mod test {
         let cond = Condition::parse(cond).unwrap();
         assert_eq!(
             cond,
-            Condition::Range(
-                ConditionRange::Greater(Version {
-                    major: 1,
-                    minor: 2,
-                    patch: 3,
-                    metadata: vec![],
-                    pre_release: vec![]
-                }),
-                None
-            ),
+            Condition::Range(vec![ConditionRange::Greater(Version {
+                major: 1,
+                minor: 2,
+                patch: 3,
+                metadata: Identifiers::new(),
+                pre_release: Identifiers::new()
+            })]),
         );
 
         let cond = ">=4.15.3-beta.1";
         let cond = Condition::parse(cond).unwrap();
         assert_eq!(
             cond,
-            Condition::Range(
-                ConditionRange::GreaterEqual(Version {
-                    major: 4,
-                    minor: 15,
-                    patch: 3,
-                    metadata: vec![],
-                    pre_release: vec!["beta".to_string(), "1".to_string()]
-                }),
-                None
-            ),
+            Condition::Range(vec![ConditionRange::GreaterEqual(Version {
+                major: 4,
+                minor: 15,
+                patch: 3,
+                metadata: Identifiers::new(),
+                pre_release: ["beta", "1"].into_iter().collect()
+            })]),
         );
 
         let cond = ">1.2.3 <4.15.3-beta.1";
         let cond = Condition::parse(cond).unwrap();
         assert_eq!(
             cond,
-            Condition::Range(
+            Condition::Range(vec![
                 ConditionRange::Greater(Version {
                     major: 1,
                     minor: 2,
                     patch: 3,
-                    metadata: vec![],
-                    pre_release: vec![]
+                    metadata: Identifiers::new(),
+                    pre_release: Identifiers::new()
                 }),
-                Some(ConditionRange::Less(Version {
+                ConditionRange::Less(Version {
                     major: 4,
                     minor: 15,
                     patch: 3,
-                    metadata: vec![],
-                    pre_release: vec!["beta".to_string(), "1".to_string()]
-                })),
-            ),
+                    metadata: Identifiers::new(),
+                    pre_release: ["beta", "1"].into_iter().collect()
+                }),
+            ]),
         );
 
         let cond = ">=1.2.3 <4.15.3";
         let cond = Condition::parse(cond).unwrap();
         assert_eq!(
             cond,
-            Condition::Range(
+            Condition::Range(vec![
                 ConditionRange::GreaterEqual(Version {
                     major: 1,
                     minor: 2,
                     patch: 3,
-                    metadata: vec![],
-                    pre_release: vec![]
+                    metadata: Identifiers::new(),
+                    pre_release: Identifiers::new()
                 }),
-                Some(ConditionRange::Less(Version {
+                ConditionRange::Less(Version {
                     major: 4,
                     minor: 15,
                     patch: 3,
-                    metadata: vec![],
-                    pre_release: vec![]
-                })),
-            ),
+                    metadata: Identifiers::new(),
+                    pre_release: Identifiers::new()
+                }),
+            ]),
         );
 
         let cond = ">=1.2.3 <=4.15.3";
         let cond = Condition::parse(cond).unwrap();
         assert_eq!(
             cond,
-            Condition::Range(
+            Condition::Range(vec![
                 ConditionRange::GreaterEqual(Version {
                     major: 1,
                     minor: 2,
                     patch: 3,
-                    metadata: vec![],
-                    pre_release: vec![]
+                    metadata: Identifiers::new(),
+                    pre_release: Identifiers::new()
                 }),
-                Some(ConditionRange::LessEqual(Version {
+                ConditionRange::LessEqual(Version {
                     major: 4,
                     minor: 15,
                     patch: 3,
-                    metadata: vec![],
-                    pre_release: vec![]
-                })),
-            ),
+                    metadata: Identifiers::new(),
+                    pre_release: Identifiers::new()
+                }),
+            ]),
         );
     }
 
@@ -358,22 +1069,22 @@ mod test {
         assert_eq!(
             cond,
             Condition::Composite(vec![
-                Condition::Range(
+                Condition::Range(vec![
                     ConditionRange::GreaterEqual(Version {
                         major: 1,
                         minor: 2,
                         patch: 3,
                         ..Default::default()
                     }),
-                    Some(ConditionRange::LessEqual(Version {
+                    ConditionRange::LessEqual(Version {
                         major: 4,
                         minor: 15,
                         patch: 3,
                         ..Default::default()
-                    })),
-                ),
-                Condition::Simple(Version {
-                    major: 5,
+                    }),
+                ]),
+                Condition::Partial(PartialVersion {
+                    major: Some(5),
                     ..Default::default()
                 })
             ])
@@ -384,24 +1095,24 @@ mod test {
         assert_eq!(
             cond,
             Condition::Composite(vec![
-                Condition::Simple(Version {
-                    major: 1,
+                Condition::Partial(PartialVersion {
+                    major: Some(1),
                     ..Default::default()
                 }),
-                Condition::Simple(Version {
-                    major: 2,
+                Condition::Partial(PartialVersion {
+                    major: Some(2),
                     ..Default::default()
                 }),
-                Condition::Simple(Version {
-                    major: 3,
+                Condition::Partial(PartialVersion {
+                    major: Some(3),
                     ..Default::default()
                 }),
-                Condition::Simple(Version {
-                    major: 4,
+                Condition::Partial(PartialVersion {
+                    major: Some(4),
                     ..Default::default()
                 }),
-                Condition::CompatibleWithMostRecent(Version {
-                    major: 5,
+                Condition::CompatibleWithMostRecent(PartialVersion {
+                    major: Some(5),
                     ..Default::default()
                 })
             ])
@@ -515,7 +1226,7 @@ mod test {
             patch: 12,
             ..Default::default()
         }));
-        assert!(!cond.compare(&Version {
+        assert!(cond.compare(&Version {
             major: 8,
             minor: 2,
             patch: 1,
@@ -654,4 +1365,897 @@ mod test {
             ..Default::default()
         }));
     }
+
+    #[test]
+    fn compare_large_components() {
+        let cond = Condition::parse(">=2.0.0").unwrap();
+        assert!(!cond.compare(&Version {
+            major: 1,
+            minor: 10000,
+            ..Default::default()
+        }));
+
+        let cond = Condition::parse(">1.9999.0 <2.0.0").unwrap();
+        assert!(cond.compare(&Version {
+            major: 1,
+            minor: 10000,
+            ..Default::default()
+        }));
+        assert!(cond.compare(&Version {
+            major: 1,
+            minor: 9999,
+            patch: 10000,
+            ..Default::default()
+        }));
+    }
+
+    #[test]
+    fn compare_pre_release() {
+        let version = |v: &str| Version::parse(v).unwrap();
+
+        let cond = Condition::parse(">=1.0.0").unwrap();
+        assert!(cond.compare(&version("2.0.0")));
+        assert!(!cond.compare(&version("2.0.0-alpha")));
+
+        let cond = Condition::parse("^1.2.3-beta.1").unwrap();
+        assert!(cond.compare(&version("1.2.3-beta.1")));
+        assert!(cond.compare(&version("1.2.3-beta.2")));
+        assert!(cond.compare(&version("1.2.3")));
+        assert!(cond.compare(&version("1.9.0")));
+        assert!(!cond.compare(&version("1.2.3-beta.0")));
+        assert!(!cond.compare(&version("1.2.3-alpha.9")));
+        assert!(!cond.compare(&version("1.2.4-beta.1")));
+
+        let cond = Condition::parse(">1.2.3-alpha.3 <1.2.4").unwrap();
+        assert!(cond.compare(&version("1.2.3-alpha.7")));
+        assert!(!cond.compare(&version("3.4.5-alpha.9")));
+
+        let cond = Condition::parse("*").unwrap();
+        assert!(!cond.compare(&version("1.0.0-rc.1")));
+
+        let cond = Condition::parse("1.0.0-rc.1 || ^2.0.0").unwrap();
+        assert!(cond.compare(&version("1.0.0-rc.1")));
+        assert!(!cond.compare(&version("2.1.0-rc.1")));
+    }
+
+    #[test]
+    fn compare_include_prerelease() {
+        let version = |v: &str| Version::parse(v).unwrap();
+        let options = CompareOptions {
+            include_prerelease: true,
+        };
+
+        let cond = Condition::parse(">=1.0.0").unwrap();
+        assert!(cond.compare_with_options(&version("2.0.0-alpha"), &options));
+
+        let floors = [
+            ("1.x", "1.0.0-beta"),
+            ("^1.2", "1.2.0-beta"),
+            (">=1.2", "1.2.0-beta"),
+            (">1.2", "1.3.0-0"),
+            ("1 - 2", "1.0.0-0"),
+            ("1.2.3 - 2", "1.2.3-0"),
+        ];
+        for (cond, v) in floors {
+            let cond = Condition::parse(cond).unwrap();
+            assert!(
+                cond.compare_with_options(&version(v), &options),
+                "{cond} {v}"
+            );
+            assert!(!cond.compare(&version(v)), "{cond} {v}");
+        }
+
+        let cond = Condition::parse("^1.2.3").unwrap();
+        assert!(!cond.compare_with_options(&version("1.2.3-beta"), &options));
+        let cond = Condition::parse("~1.2").unwrap();
+        assert!(!cond.compare_with_options(&version("1.2.0-beta"), &options));
+        assert_eq!(
+            Condition::parse("1.x || 1.2.3 - 2")
+                .unwrap()
+                .to_comparator_sets_with_options(&options)
+                .iter()
+                .map(|set| set
+                    .iter()
+                    .map(|c| c.to_string())
+                    .collect::<Vec<String>>()
+                    .join(" "))
+                .collect::<Vec<String>>(),
+            vec![">=1.0.0-0 <2.0.0-0", ">=1.2.3-0 <3.0.0-0"]
+        );
+
+        let cond = Condition::parse("*").unwrap();
+        assert!(cond.compare_with_options(&version("1.0.0-rc.1"), &options));
+
+        let cond = Condition::parse("^1.2.3").unwrap();
+        assert!(cond.compare_with_options(&version("1.5.0-beta"), &options));
+        assert!(!cond.compare_with_options(&version("2.0.0-beta"), &options));
+        assert!(!cond.compare_with_options(&version("1.2.3-beta"), &options));
+
+        let cond = Condition::parse("~1.2.3").unwrap();
+        assert!(cond.compare_with_options(&version("1.2.5-beta"), &options));
+        assert!(!cond.compare_with_options(&version("1.3.0-beta"), &options));
+    }
+
+    #[test]
+    fn hyphen_cases() {
+        let cond = Condition::parse("1.2.3 - 2.3.4").unwrap();
+        assert_eq!(
+            cond,
+            Condition::HyphenRange(
+                PartialVersion {
+                    major: Some(1),
+                    minor: Some(2),
+                    patch: Some(3),
+                    ..Default::default()
+                },
+                PartialVersion {
+                    major: Some(2),
+                    minor: Some(3),
+                    patch: Some(4),
+                    ..Default::default()
+                },
+            )
+        );
+        assert_eq!(cond.to_string(), "1.2.3 - 2.3.4");
+
+        let version = |v: &str| Version::parse(v).unwrap();
+        assert!(cond.compare(&version("1.2.3")));
+        assert!(cond.compare(&version("2.3.4")));
+        assert!(!cond.compare(&version("2.3.5")));
+        assert!(!cond.compare(&version("1.2.2")));
+
+        let cond = Condition::parse("1.2 - 2.3").unwrap();
+        assert_eq!(cond.to_string(), "1.2 - 2.3");
+        assert!(cond.compare(&version("1.2.0")));
+        assert!(cond.compare(&version("2.3.99")));
+        assert!(!cond.compare(&version("2.4.0")));
+        assert!(!cond.compare(&version("2.4.0-0")));
+        assert!(!cond.compare(&version("1.1.99")));
+
+        let cond = Condition::parse("1 - 2").unwrap();
+        assert!(cond.compare(&version("2.99.99")));
+        assert!(!cond.compare(&version("3.0.0")));
+
+        let cond = Condition::parse("1.2.3-beta.2 - 2.0.0").unwrap();
+        assert_eq!(cond.to_string(), "1.2.3-beta.2 - 2.0.0");
+        assert!(cond.compare(&version("1.2.3-beta.4")));
+        assert!(!cond.compare(&version("1.2.3-beta.1")));
+
+        let cond = Condition::parse("1.2.3-2.3.4").unwrap();
+        assert!(matches!(cond, Condition::Simple(_)));
+
+        let cond = Condition::parse("1.0.0 - 1.2.0 || 2.0.0 - 3").unwrap();
+        assert_eq!(cond.to_string(), "1.0.0 - 1.2.0 || 2.0.0 - 3");
+        assert!(cond.compare(&version("3.9.0")));
+        assert!(!cond.compare(&version("1.5.0")));
+
+        for (input, written) in [
+            ("* - 2", "* - 2"),
+            ("1.2.3 - *", "1.2.3 - *"),
+            ("1.x - *", "1 - *"),
+        ] {
+            let cond = Condition::parse(input).unwrap();
+            assert_eq!(cond.to_string(), written);
+            assert_eq!(Condition::parse(written).unwrap(), cond);
+        }
+
+        let loose = ParseOptions { loose: true };
+        let cond = Condition::parse_with_options("x - 900x0x0", &loose).unwrap();
+        assert_eq!(cond.to_string(), "* - 900-x0x0");
+        assert_eq!(Condition::parse(&cond.to_string()).unwrap(), cond);
+    }
+
+    #[test]
+    fn x_range_cases() {
+        let version = |v: &str| Version::parse(v).unwrap();
+
+        for cond in ["*", "x", "X", "*.*.*", "x.x"] {
+            assert_eq!(Condition::parse(cond).unwrap(), Condition::Any);
+        }
+
+        for cond in ["1", "1.x", "1.X", "1.*", "1.x.x"] {
+            let cond = Condition::parse(cond).unwrap();
+            assert_eq!(cond.to_string(), "1");
+            assert!(cond.compare(&version("1.0.0")));
+            assert!(cond.compare(&version("1.99.99")));
+            assert!(!cond.compare(&version("2.0.0")));
+            assert!(!cond.compare(&version("0.9.9")));
+            assert!(!cond.compare(&version("1.5.0-beta")));
+        }
+
+        for cond in ["1.2", "1.2.x", "1.2.*"] {
+            let cond = Condition::parse(cond).unwrap();
+            assert_eq!(cond.to_string(), "1.2");
+            assert!(cond.compare(&version("1.2.0")));
+            assert!(cond.compare(&version("1.2.99")));
+            assert!(!cond.compare(&version("1.3.0")));
+            assert!(!cond.compare(&version("1.1.9")));
+        }
+
+        let cond = Condition::parse("1.x || 3.2.x").unwrap();
+        assert!(cond.compare(&version("1.4.0")));
+        assert!(cond.compare(&version("3.2.7")));
+        assert!(!cond.compare(&version("3.3.0")));
+
+        let cond = Condition::parse("1.x - 2.3").unwrap();
+        assert!(cond.compare(&version("1.0.0")));
+        assert!(cond.compare(&version("2.3.9")));
+        assert!(!cond.compare(&version("2.4.0")));
+
+        let cond = Condition::parse("* - 2").unwrap();
+        assert!(cond.compare(&version("0.0.1")));
+        assert!(!cond.compare(&version("3.0.0")));
+    }
+
+    #[test]
+    fn comparator_set_cases() {
+        let version = |v: &str| Version::parse(v).unwrap();
+        let range = |cond: &str| {
+            Condition::parse(cond)
+                .unwrap()
+                .comparators(&CompareOptions::default())
+                .iter()
+                .map(|r| r.to_string())
+                .collect::<Vec<String>>()
+        };
+
+        assert_eq!(range("<2.0.0"), vec!["<2.0.0"]);
+        assert_eq!(range("<=1.4"), vec!["<1.5.0-0"]);
+        assert_eq!(range("<1 >=0.5"), vec!["<1.0.0-0", ">=0.5.0"]);
+        assert_eq!(range(">1"), vec![">=2.0.0"]);
+        assert_eq!(range(">1.2"), vec![">=1.3.0"]);
+        assert_eq!(range(">=1.2"), vec![">=1.2.0"]);
+        assert_eq!(range("<=1"), vec!["<2.0.0-0"]);
+        assert_eq!(range(">x"), vec!["<0.0.0-0"]);
+        assert_eq!(range(">= 1.2.3 <= 2.0.0"), vec![">=1.2.3", "<=2.0.0"]);
+        assert_eq!(
+            range("<1.5.0 >=1.2.0 <1.4.0"),
+            vec!["<1.5.0", ">=1.2.0", "<1.4.0"]
+        );
+        assert_eq!(
+            range("^1.2.3 <1.5.0"),
+            vec![">=1.2.3", "<2.0.0-0", "<1.5.0"]
+        );
+        assert_eq!(range("1.x >=1.2.0"), vec![">=1.0.0", "<2.0.0-0", ">=1.2.0"]);
+        assert_eq!(range("=1.2.3 >1.0.0"), vec![">=1.2.3", "<=1.2.3", ">1.0.0"]);
+
+        assert_eq!(Condition::parse(">=*").unwrap(), Condition::Any);
+        for cond in ["* *", "x X", "^* x"] {
+            let cond = Condition::parse(cond).unwrap();
+            assert_eq!(cond, Condition::Any);
+            assert_eq!(cond.to_string(), "*");
+        }
+
+        for cond in [
+            "^1.2.3 <1.5.0",
+            "1 >=1.2.0",
+            "1.2.3 >1.0.0",
+            "<=1.4",
+            ">1 <2.0.0 ~1.2",
+        ] {
+            assert_eq!(Condition::parse(cond).unwrap().to_string(), cond);
+        }
+        assert_eq!(
+            Condition::parse("^1.2.3 <1.5.0").unwrap(),
+            Condition::Intersection(vec![
+                Condition::CompatibleWithMostRecent(version("1.2.3").into()),
+                Condition::Range(vec![ConditionRange::Less(version("1.5.0"))]),
+            ])
+        );
+        assert_eq!(
+            Condition::parse(">=1.2").unwrap(),
+            Condition::PartialRange(
+                Operator::GreaterEqual,
+                PartialVersion::parse("1.2").unwrap()
+            )
+        );
+        assert_eq!(
+            Condition::parse(">=1.2.3 * <2.0.0").unwrap(),
+            Condition::parse(">=1.2.3 <2.0.0").unwrap()
+        );
+
+        let loose = ParseOptions { loose: true };
+        for (cond, written) in [("1.2.3<2", "1.2.3 <2"), (">=1.2.3<2.0.0", ">=1.2.3 <2.0.0")] {
+            assert!(Condition::parse(cond).is_err(), "{cond}");
+            let cond = Condition::parse_with_options(cond, &loose).unwrap();
+            assert_eq!(cond.to_string(), written);
+        }
+        assert!(Condition::parse("x=*").is_err());
+        assert_eq!(
+            Condition::parse_with_options("x=*", &loose).unwrap(),
+            Condition::Any
+        );
+        assert_eq!(
+            Condition::parse("=1.2.3").unwrap(),
+            Condition::Simple(version("1.2.3"))
+        );
+
+        let cond = Condition::parse("<2.0.0").unwrap();
+        assert!(cond.compare(&version("1.9.9")));
+        assert!(cond.compare(&version("0.0.0")));
+        assert!(!cond.compare(&version("2.0.0")));
+
+        let cond = Condition::parse("<1 >=0.5").unwrap();
+        assert!(cond.compare(&version("0.5.0")));
+        assert!(cond.compare(&version("0.9.9")));
+        assert!(!cond.compare(&version("1.0.0")));
+        assert!(!cond.compare(&version("0.4.9")));
+
+        let cond = Condition::parse("^1.2.3 <1.5.0").unwrap();
+        assert!(cond.compare(&version("1.4.9")));
+        assert!(!cond.compare(&version("1.5.0")));
+        assert!(!cond.compare(&version("1.2.2")));
+
+        let cond = Condition::parse(">x").unwrap();
+        assert!(!cond.compare(&version("0.0.0")));
+
+        let cond = Condition::parse("<1.2.3 || >2").unwrap();
+        assert!(cond.compare(&version("1.2.2")));
+        assert!(!cond.compare(&version("2.5.0")));
+        assert!(cond.compare(&version("3.0.0")));
+    }
+
+    fn lowered(cond: &str) -> String {
+        Condition::parse(cond)
+            .unwrap()
+            .comparators(&CompareOptions::default())
+            .iter()
+            .map(|r| r.to_string())
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+
+    #[test]
+    fn caret_cases() {
+        let table = [
+            ("^1.2.3", ">=1.2.3 <2.0.0-0"),
+            ("^1.2.0", ">=1.2.0 <2.0.0-0"),
+            ("^1.2", ">=1.2.0 <2.0.0-0"),
+            ("^1.2.x", ">=1.2.0 <2.0.0-0"),
+            ("^1", ">=1.0.0 <2.0.0-0"),
+            ("^1.x", ">=1.0.0 <2.0.0-0"),
+            ("^0.2.3", ">=0.2.3 <0.3.0-0"),
+            ("^0.1", ">=0.1.0 <0.2.0-0"),
+            ("^0.1.x", ">=0.1.0 <0.2.0-0"),
+            ("^0.0.3", ">=0.0.3 <0.0.4-0"),
+            ("^0.0.0", ">=0.0.0 <0.0.1-0"),
+            ("^0.0", ">=0.0.0 <0.1.0-0"),
+            ("^0.0.x", ">=0.0.0 <0.1.0-0"),
+            ("^0.x", ">=0.0.0 <1.0.0-0"),
+            ("^0", ">=0.0.0 <1.0.0-0"),
+            ("^1.2.3-beta.2", ">=1.2.3-beta.2 <2.0.0-0"),
+            ("^0.1.2-beta", ">=0.1.2-beta <0.2.0-0"),
+            ("^0.0.1-beta", ">=0.0.1-beta <0.0.2-0"),
+            ("^x", ""),
+        ];
+        for (cond, expected) in table {
+            assert_eq!(lowered(cond), expected, "{cond}");
+        }
+
+        let version = |v: &str| Version::parse(v).unwrap();
+        let satisfied = [
+            ("^1.2.3", "1.8.1"),
+            ("^0.1.2", "0.1.2"),
+            ("^0.1", "0.1.2"),
+            ("^0.0.1", "0.0.1"),
+            ("^1.2", "1.4.2"),
+            ("^1.2 ^1", "1.4.2"),
+            ("^1.2.3-alpha", "1.2.3-pre"),
+            ("^1.2.0-alpha", "1.2.0-pre"),
+            ("^0.0.1-alpha", "0.0.1-beta"),
+            ("^0.0.1-alpha", "0.0.1"),
+            ("^0.1.1-alpha", "0.1.1-beta"),
+            ("^x", "1.2.3"),
+            ("^0.x", "0.9.9"),
+        ];
+        for (cond, v) in satisfied {
+            assert!(
+                Condition::parse(cond).unwrap().compare(&version(v)),
+                "{cond} {v}"
+            );
+        }
+
+        let unsatisfied = [
+            ("^0.2.3", "0.9.0"),
+            ("^0.0.3", "0.0.9"),
+            ("^0.1.2", "0.2.0"),
+            ("^1.2.3", "2.0.0"),
+            ("^1.2.3", "1.2.2"),
+            ("^1.2", "1.1.9"),
+            ("^0.0.1", "0.0.2"),
+            ("^1.2.3-beta", "2.0.0-alpha"),
+            ("^1.2.3", "1.2.3-beta"),
+            ("^1.2.3-beta.2", "1.2.3-beta.1"),
+            ("^0.x", "1.0.0"),
+        ];
+        for (cond, v) in unsatisfied {
+            assert!(
+                !Condition::parse(cond).unwrap().compare(&version(v)),
+                "{cond} {v}"
+            );
+        }
+    }
+
+    #[test]
+    fn tilde_cases() {
+        let table = [
+            ("~1.2.3", ">=1.2.3 <1.3.0-0"),
+            ("~1.2", ">=1.2.0 <1.3.0-0"),
+            ("~1.2.x", ">=1.2.0 <1.3.0-0"),
+            ("~1", ">=1.0.0 <2.0.0-0"),
+            ("~1.x", ">=1.0.0 <2.0.0-0"),
+            ("~0.2.3", ">=0.2.3 <0.3.0-0"),
+            ("~0.2", ">=0.2.0 <0.3.0-0"),
+            ("~0", ">=0.0.0 <1.0.0-0"),
+            ("~0.2.3-beta", ">=0.2.3-beta <0.3.0-0"),
+            ("~1.2.3-beta.2", ">=1.2.3-beta.2 <1.3.0-0"),
+            ("~ 1.0", ">=1.0.0 <1.1.0-0"),
+            ("~>3.2.1", ">=3.2.1 <3.3.0-0"),
+            ("~>1.2", ">=1.2.0 <1.3.0-0"),
+            ("~> 1", ">=1.0.0 <2.0.0-0"),
+            ("~*", ""),
+        ];
+        for (cond, expected) in table {
+            assert_eq!(lowered(cond), expected, "{cond}");
+        }
+
+        let version = |v: &str| Version::parse(v).unwrap();
+        let satisfied = [
+            ("~1.2.3-beta.2", "1.2.3-beta.4"),
+            ("~0.2.3-beta", "0.2.3"),
+            ("~0.2.3-beta", "0.2.4"),
+            ("~1", "1.2.3"),
+            ("~1.0", "1.0.2"),
+            ("~ 1.0", "1.0.2"),
+            ("~2.4", "2.4.5"),
+            ("~1.x", "1.9.0"),
+            ("~x", "0.0.9"),
+        ];
+        for (cond, v) in satisfied {
+            assert!(
+                Condition::parse(cond).unwrap().compare(&version(v)),
+                "{cond} {v}"
+            );
+        }
+
+        let unsatisfied = [
+            ("~1", "2.0.0"),
+            ("~1.2", "1.3.0"),
+            ("~1.0.0", "1.1.0"),
+            ("~0.2.3", "0.3.0"),
+            ("~2.4", "2.5.0"),
+            ("~2.4", "2.3.9"),
+            ("~0.2.3-beta", "0.2.4-beta"),
+            ("~1.2.3-beta.2", "1.2.4-beta.2"),
+            ("~1.2.3", "1.2.3-beta"),
+        ];
+        for (cond, v) in unsatisfied {
+            assert!(
+                !Condition::parse(cond).unwrap().compare(&version(v)),
+                "{cond} {v}"
+            );
+        }
+
+        let cond = Condition::parse("~1").unwrap();
+        assert_ne!(cond, Condition::parse("~1.0.0").unwrap());
+        assert_eq!(cond.to_string(), "~1");
+    }
+
+    #[test]
+    fn comparator_sets() {
+        let sets = |cond: &str| {
+            Condition::parse(cond)
+                .unwrap()
+                .to_comparator_sets()
+                .iter()
+                .map(|set| {
+                    set.iter()
+                        .map(|c| c.to_string())
+                        .collect::<Vec<String>>()
+                        .join(" ")
+                })
+                .collect::<Vec<String>>()
+        };
+
+        assert_eq!(sets("*"), vec![""]);
+        assert_eq!(sets("1.2.3"), vec![">=1.2.3 <=1.2.3"]);
+        assert_eq!(sets("1.2"), vec![">=1.2.0 <1.3.0-0"]);
+        assert_eq!(sets("1.2.3 - 2"), vec![">=1.2.3 <3.0.0-0"]);
+        assert_eq!(sets("1.2.3 - 2.0.0"), vec![">=1.2.3 <=2.0.0"]);
+        assert_eq!(sets("* - 2.0.0"), vec!["<=2.0.0"]);
+        assert_eq!(
+            sets("^1.2.3 || ~0.2 || >=3 <4 || 5.x"),
+            vec![
+                ">=1.2.3 <2.0.0-0",
+                ">=0.2.0 <0.3.0-0",
+                ">=3.0.0 <4.0.0-0",
+                ">=5.0.0 <6.0.0-0"
+            ]
+        );
+    }
+
+    #[test]
+    fn intersection() {
+        let cond = |c: &str| Condition::parse(c).unwrap();
+        let intersect = |l: &str, r: &str| cond(l).intersect(&cond(r)).map(|c| c.to_string());
+
+        assert_eq!(intersect("^1.2.0", "~1.4"), Some("~1.4.0".to_owned()));
+        assert_eq!(
+            intersect("^1.2.0", ">=1.0.0 <=1.3.0"),
+            Some(">=1.2.0 <=1.3.0".to_owned())
+        );
+        assert_eq!(
+            intersect("^1.2.0", "<=1.3.0"),
+            Some(">=1.2.0 <=1.3.0".to_owned())
+        );
+        assert_eq!(intersect("1.2.3", "1.2.3"), Some("1.2.3".to_owned()));
+        assert_eq!(intersect("^1.2.0", ">1.2.3"), Some("^1.2.4".to_owned()));
+        assert_eq!(
+            intersect(">1.2.3", "<1.5.0"),
+            Some(">1.2.3 <1.5.0".to_owned())
+        );
+        assert_eq!(
+            intersect("^1.2.0", "<1.3.1"),
+            Some(">=1.2.0 <1.3.1".to_owned())
+        );
+        assert_eq!(intersect("^1.2.0", "^2.0.0"), None);
+        assert_eq!(intersect("*", "*"), Some("*".to_owned()));
+        assert_eq!(intersect("*", "<1.0.0"), Some("<1.0.0".to_owned()));
+        assert_eq!(
+            intersect("1.x || 3.x", "^1.5.0 || ^3.2.0 || ^4"),
+            Some("^1.5.0 || ^3.2.0".to_owned())
+        );
+        assert_eq!(intersect(">1.2.3 <1.2.4", "1.2.4"), None);
+        assert_eq!(
+            intersect("^1.2.3-beta.2", "~1.2.3-beta.4"),
+            Some("~1.2.3-beta.4".to_owned())
+        );
+
+        let intersects = |l: &str, r: &str| cond(l).intersects(&cond(r));
+        assert!(intersects("^1.2.3", "1.5.x"));
+        assert!(intersects("<1.0.0 || >=2.0.0", "2.1.0"));
+        assert!(intersects("1.2.3 - 2.3.4", "~2.3.4"));
+        assert!(!intersects("1.2.3 - 2.3.4", "~2.3.5"));
+        assert!(!intersects("<1.0.0", ">=1.0.0"));
+        assert!(!intersects("<=1.0.0", ">1.0.0"));
+        assert!(intersects("<=1.0.0", ">=1.0.0"));
+        assert!(!intersects("^1.2.3", "2.0.0-alpha"));
+        assert!(!intersects("^1.2.3", "1.5.0-beta"));
+        assert!(intersects("^1.2.3-beta", "1.2.3-rc"));
+    }
+
+    #[test]
+    fn subset() {
+        let subset = |l: &str, r: &str| {
+            Condition::parse(l)
+                .unwrap()
+                .is_subset_of(&Condition::parse(r).unwrap())
+        };
+
+        assert!(subset("1.2.3", "^1.0.0"));
+        assert!(subset("~1.2.3", "^1.2.0"));
+        assert!(subset("^1.2.3", "*"));
+        assert!(subset("^1.2.3 || ^1.5.0", "1.x"));
+        assert!(subset(">=1.0.0 <2.0.0", "^1.0.0"));
+        assert!(subset("^1.0.0", ">=1.0.0 <2.0.0"));
+        assert!(subset(">=1.0.0 <1.5.0 || >=1.5.0 <2.0.0", "1.x"));
+        assert!(subset("1.x", ">=1.0.0 <1.5.0 || >=1.5.0 <2.0.0"));
+        assert!(subset(">1.2.3 <1.2.5", ">=1.2.4 <=1.2.4"));
+        assert!(subset("^1.2.3-beta.2", "^1.2.3-beta.1"));
+
+        assert!(!subset("^1.0.0", "~1.0.0"));
+        assert!(!subset("*", "^1.0.0"));
+        assert!(!subset("1.x", ">=1.0.0 <1.5.0 || >=1.6.0 <2.0.0"));
+        assert!(!subset("^1.2.3-beta.1", "^1.2.3-beta.2"));
+        assert!(!subset(">=1.2.3-beta <1.3.0", ">=1.0.0 <2.0.0"));
+    }
+
+    #[test]
+    fn simplification() {
+        let simplify = |c: &str| Condition::parse(c).unwrap().simplify().to_string();
+
+        assert_eq!(simplify("^1.0.0 || ^1.2.0 || >=1.5.0 <2.0.0"), "^1.0.0");
+        assert_eq!(simplify(">=1.0.0 <1.5.0 || >=1.5.0 <2.0.0"), "^1.0.0");
+        assert_eq!(simplify("~1.2.3 || ^1.2.0"), "^1.2.0");
+        assert_eq!(simplify("~1.2.3 || ~1.2.5"), "~1.2.3");
+        assert_eq!(simplify("1.2.3 || 1.2.4 || 1.2.5"), ">=1.2.3 <1.2.6");
+        assert_eq!(simplify("0.0.3 || ^0.0.3"), "0.0.3");
+        assert_eq!(simplify("<1.0.0 || >=1.0.0"), "*");
+        assert_eq!(simplify(">=2.0.0 || >=1.0.0 <3.0.0"), ">=1.0.0");
+        assert_eq!(simplify("^1.2.3-beta.2 || ^1.2.3"), "^1.2.3-beta.2");
+        assert_eq!(simplify("^1.0.0 || ^3.0.0 || ^1.5.0"), "^1.0.0 || ^3.0.0");
+        assert_eq!(simplify("1.x"), "1");
+        assert_eq!(simplify("1.2.3 - 2.3.4"), "1.2.3 - 2.3.4");
+        assert_eq!(simplify(">1.0.0 <1.0.0"), ">1.0.0 <1.0.0");
+    }
+
+    #[test]
+    fn simplification_with_versions() {
+        let versions = [
+            "1.0.0", "1.0.1", "1.0.2", "1.0.3", "1.0.4", "1.1.0", "1.1.1", "1.1.2", "1.2.0",
+            "1.2.1", "1.2.2", "1.2.3", "1.2.4", "1.2.5", "2.0.0", "2.0.1", "2.1.0", "2.1.1",
+            "2.1.2", "2.2.0", "2.2.1", "2.2.2", "2.3.0", "2.3.1", "2.4.0", "3.0.0", "3.1.0",
+            "3.2.0", "3.3.0",
+        ]
+        .map(|v| Version::parse(v).unwrap());
+        let simplify = |c: &str| {
+            Condition::parse(c)
+                .unwrap()
+                .simplify_with_versions(&versions)
+                .to_string()
+        };
+
+        assert_eq!(simplify("1.x"), "1");
+        assert_eq!(
+            simplify("1.0.0 || 1.0.1 || 1.0.2 || 1.0.3 || 1.0.4"),
+            "<=1.0.4"
+        );
+        assert_eq!(simplify(">=3.0.0 <3.1.0"), "3.0.0");
+        assert_eq!(simplify("3.0.0 || 3.1 || 3.2 || 3.3"), ">=3.0.0");
+        assert_eq!(simplify("1 || 2 || 3"), "*");
+        assert_eq!(simplify("2.1 || 2.2 || 2.3"), "2.1.0 - 2.3.1");
+        assert_eq!(simplify("^4.0.0"), "^4.0.0");
+    }
+
+    #[test]
+    fn satisfying() {
+        let versions = [
+            "0.9.0",
+            "1.0.0",
+            "1.2.0-beta.1",
+            "1.2.0",
+            "1.2.7",
+            "1.3.0-rc.0",
+            "1.5.0",
+            "2.0.0-alpha",
+            "2.0.0",
+            "2.1.3",
+        ]
+        .map(|v| Version::parse(v).unwrap());
+
+        let cases = [
+            ("^1.0.0", Some("1.5.0"), Some("1.0.0")),
+            ("~1.2.0", Some("1.2.7"), Some("1.2.0")),
+            ("^1.2.0-beta.0", Some("1.5.0"), Some("1.2.0-beta.1")),
+            (
+                ">=1.3.0-rc.0 <1.3.0",
+                Some("1.3.0-rc.0"),
+                Some("1.3.0-rc.0"),
+            ),
+            ("*", Some("2.1.3"), Some("0.9.0")),
+            (">=2.0.0-0", Some("2.1.3"), Some("2.0.0-alpha")),
+            ("<1.0.0 || >2.0.0", Some("2.1.3"), Some("0.9.0")),
+            ("1.0.0 - 1.2", Some("1.2.7"), Some("1.0.0")),
+            ("^3.0.0", None, None),
+            ("1.3.x", None, None),
+        ];
+
+        for (cond, max, min) in cases {
+            let cond = Condition::parse(cond).unwrap();
+            let max = max.map(|v| Version::parse(v).unwrap());
+            let min = min.map(|v| Version::parse(v).unwrap());
+
+            assert_eq!(cond.max_satisfying(&versions), max.as_ref(), "{cond}");
+            assert_eq!(cond.min_satisfying(&versions), min.as_ref(), "{cond}");
+            assert_eq!(
+                cond.max_satisfying_sorted(&versions),
+                max.as_ref(),
+                "{cond}"
+            );
+            assert_eq!(
+                cond.min_satisfying_sorted(&versions),
+                min.as_ref(),
+                "{cond}"
+            );
+        }
+
+        let cond = Condition::parse("^1.2.0").unwrap();
+        let matching = cond
+            .filter_satisfying(&versions)
+            .map(|v| v.to_string())
+            .collect::<Vec<String>>();
+        assert_eq!(matching, vec!["1.2.0", "1.2.7", "1.5.0"]);
+    }
+
+    #[test]
+    fn min_version() {
+        let cases = [
+            ("*", Some("0.0.0")),
+            ("* || >=2", Some("0.0.0")),
+            (">=1.0.0", Some("1.0.0")),
+            (">1.0.0", Some("1.0.1")),
+            (">1.0.0-0", Some("1.0.0-0.0")),
+            (">1.0.0-beta", Some("1.0.0-beta.0")),
+            ("^1.2.3", Some("1.2.3")),
+            ("^1.2.3-beta", Some("1.2.3-beta")),
+            ("~1.2", Some("1.2.0")),
+            ("<1.0.0", Some("0.0.0")),
+            (">=2 || >1.0.0", Some("1.0.1")),
+            ("<0.0.0-beta", Some("0.0.0-0")),
+            ("<0.0.1-beta", Some("0.0.0")),
+            ("1.2.3 - 1.5", Some("1.2.3")),
+            (">=1.0.0 <=1.0.0", Some("1.0.0")),
+            (">1.0.0 <1.0.1", None),
+            (">2.0.0 <1.0.0", None),
+        ];
+
+        for (cond, expected) in cases {
+            let expected = expected.map(|v| Version::parse(v).unwrap());
+            assert_eq!(
+                Condition::parse(cond).unwrap().min_version(),
+                expected,
+                "{cond}"
+            );
+        }
+    }
+
+    #[test]
+    fn upper_bound() {
+        let upper = |c: &str| {
+            Condition::parse(c)
+                .unwrap()
+                .upper_bound()
+                .map(|r| r.to_string())
+        };
+
+        assert_eq!(upper("^1.2.3"), Some("<2.0.0-0".to_owned()));
+        assert_eq!(upper("~1.2 || 2.x"), Some("<3.0.0-0".to_owned()));
+        assert_eq!(upper("<=1.2.3 || 1.0.0"), Some("<=1.2.3".to_owned()));
+        assert_eq!(upper("1.2.3 - 2.3.4"), Some("<=2.3.4".to_owned()));
+        assert_eq!(upper(">2.0.0 <1.0.0"), Some("<0.0.0-0".to_owned()));
+        assert_eq!(upper(">2.0.0 <1.0.0 || <1.5.0"), Some("<1.5.0".to_owned()));
+        assert_eq!(upper(">=1"), None);
+        assert_eq!(upper("*"), None);
+        assert_eq!(upper("<1 || >=2"), None);
+    }
+
+    #[test]
+    fn outside() {
+        let cases = [
+            ("^1.2.3", "2.0.0", Some(Side::Above)),
+            ("^1.2.3", "2.0.0-0", Some(Side::Above)),
+            ("^1.2.3", "1.2.2", Some(Side::Below)),
+            ("^1.2.3", "1.2.3-beta", Some(Side::Below)),
+            ("^1.2.3", "1.5.0", None),
+            ("^1.2.3", "1.5.0-beta", None),
+            ("~1.2 || 2.x", "3.0.0", Some(Side::Above)),
+            ("~1.2 || 2.x", "1.1.9", Some(Side::Below)),
+            ("~1.2 || 2.x", "1.5.0", None),
+            ("<1 || >=2", "1.5.0", None),
+            ("<=1.2.3", "1.2.4", Some(Side::Above)),
+            (">1.2.3", "1.2.3", Some(Side::Below)),
+            ("*", "1.0.0-beta", None),
+            (">2.0.0 <1.0.0", "1.5.0", None),
+        ];
+
+        for (cond, version, expected) in cases {
+            let cond = Condition::parse(cond).unwrap();
+            let version = Version::parse(version).unwrap();
+            assert_eq!(cond.outside(&version), expected, "{cond} {version}");
+            assert_eq!(cond.is_above(&version), expected == Some(Side::Above));
+            assert_eq!(cond.is_below(&version), expected == Some(Side::Below));
+        }
+    }
+
+    #[test]
+    fn loose_parsing() {
+        let loose = ParseOptions { loose: true };
+        let version = |v: &str| Version::parse(v).unwrap();
+
+        let cond = Condition::parse_with_options(">=1.2.3beta <2", &loose).unwrap();
+        assert!(cond.compare(&version("1.2.3-beta.1")));
+        assert!(cond.compare(&version("1.5.0")));
+        assert!(Condition::parse(">=1.2.3beta <2").is_err());
+
+        let cond = Condition::parse_with_options("~01.2 || ^v3", &loose).unwrap();
+        assert_eq!(cond.to_string(), "~1.2 || ^3");
+        assert!(Condition::parse("~01.2").is_err());
+
+        let cond = Condition::parse("v1.2.3 || >=v2").unwrap();
+        assert!(cond.compare(&version("1.2.3")));
+        assert!(cond.compare(&version("2.5.0")));
+    }
+
+    #[test]
+    fn errors() {
+        let error = Condition::parse(">=1.2.3 <2..0").unwrap_err();
+        assert_eq!(error.kind(), &ErrorKind::UnexpectedToken);
+        assert_eq!(error.span(), Span::new(11, 12));
+        assert_eq!(error.expected(), &[Expected::Number, Expected::Wildcard]);
+        assert_eq!(
+            error.to_string(),
+            "unexpected `.` at 11, expected a number or a wildcard"
+        );
+
+        let error = Condition::parse("1.2.3 || ").unwrap_err();
+        assert_eq!(error.kind(), &ErrorKind::UnexpectedEnd);
+        assert_eq!(error.span(), Span::at(8));
+        assert_eq!(error.expected(), &[Expected::Version]);
+
+        let error = Condition::parse("|| 1.2.3").unwrap_err();
+        assert_eq!(error.kind(), &ErrorKind::UnexpectedToken);
+        assert_eq!(error.fragment(), "||");
+
+        let error = Condition::parse(">=").unwrap_err();
+        assert_eq!(error.kind(), &ErrorKind::UnexpectedEnd);
+        assert_eq!(error.span(), Span::at(2));
+
+        let error = Condition::parse("^1.2.3 !").unwrap_err();
+        assert_eq!(error.kind(), &ErrorKind::InvalidCharacter('!'));
+        assert_eq!(error.span(), Span::new(7, 8));
+
+        let error = Condition::parse("  ~1.2.3-").unwrap_err();
+        assert_eq!(
+            error.snippet(),
+            "unexpected end of input, expected an identifier\n  ~1.2.3-\n         ^"
+        );
+
+        let error = Condition::parse("   ").unwrap_err();
+        assert_eq!(error.kind(), &ErrorKind::EmptyInput);
+    }
+
+    #[test]
+    fn large_components() {
+        let version = |v: &str| Version::parse(v).unwrap();
+
+        let cond = Condition::parse("^2023101612345.1").unwrap();
+        assert!(cond.compare(&version("2023101612345.9.0")));
+        assert!(!cond.compare(&version("2023101612346.0.0")));
+
+        let cond = Condition::parse(&format!("^{}", u64::MAX)).unwrap();
+        assert!(!cond.compare(&version("1.0.0")));
+
+        let max = u64::MAX;
+        let satisfied = [
+            (format!("^{max}"), format!("{max}.5.0")),
+            (format!("^0.0.{max}"), format!("0.0.{max}")),
+            (format!("^0.{max}"), format!("0.{max}.3")),
+            (format!("~1.{max}.0"), format!("1.{max}.7")),
+            (format!("~{max}"), format!("{max}.2.0")),
+            (format!("{max}.x"), format!("{max}.2.0")),
+            (format!("1.{max}"), format!("1.{max}.9")),
+            (format!("<={max}"), format!("{max}.9.9")),
+            (format!("<=1.{max}"), format!("1.{max}.9")),
+            (format!("1 - {max}"), format!("{max}.9.9")),
+            (format!("<={max}.{max}.{max}"), format!("{max}.{max}.{max}")),
+        ];
+        for (cond, v) in satisfied {
+            assert!(
+                Condition::parse(&cond).unwrap().compare(&version(&v)),
+                "{cond} {v}"
+            );
+        }
+
+        let unsatisfied = [
+            (format!("^0.0.{max}"), "0.1.0".to_owned()),
+            (format!("~1.{max}.0"), "2.0.0".to_owned()),
+            (format!("<=1.{max}"), "2.0.0".to_owned()),
+            (format!(">{max}"), format!("{max}.9.9")),
+            (format!(">1.{max}"), format!("1.{max}.9")),
+        ];
+        for (cond, v) in unsatisfied {
+            assert!(
+                !Condition::parse(&cond).unwrap().compare(&version(&v)),
+                "{cond} {v}"
+            );
+        }
+        assert!(Condition::parse(&format!(">1.{max}"))
+            .unwrap()
+            .compare(&version("2.0.0")));
+
+        let cond = Condition::parse(&format!("<={max}")).unwrap();
+        assert_eq!(cond.to_string(), format!("<={max}"));
+        assert_eq!(lowered(&format!("<={max}")), "");
+        assert_eq!(
+            lowered(&format!("~1.{max}.0")),
+            format!(">=1.{max}.0 <2.0.0-0")
+        );
+        assert_eq!(
+            Condition::parse(&format!(">={max}.{max}.{max}"))
+                .unwrap()
+                .simplify()
+                .to_string(),
+            format!(">={max}.{max}.{max}")
+        );
+    }
 }
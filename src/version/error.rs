@@ -0,0 +1,243 @@
+use std::fmt::Display;
+
+/// A byte range into the parsed input.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Span {
+    /// The first byte.
+    pub start: usize,
+    /// The byte after the last one.
+    pub end: usize,
+}
+
+impl Span {
+    /// The bytes from `start` up to `end`.
+    pub fn new(start: usize, end: usize) -> Self {
+        Span { start, end }
+    }
+
+    /// An empty span, used to point in between two characters.
+    pub fn at(position: usize) -> Self {
+        Span::new(position, position)
+    }
+}
+
+/// What is wrong with the input.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ErrorKind {
+    /// There is nothing to parse.
+    EmptyInput,
+    /// A character that has no place in a version or a range.
+    InvalidCharacter(char),
+    /// Something other than what was expected.
+    UnexpectedToken,
+    /// The input ends before it is complete.
+    UnexpectedEnd,
+    /// A number with a leading zero, where strict parsing forbids one.
+    LeadingZero,
+    /// A number too large for a `u64`.
+    Overflow,
+}
+
+/// What the parser would have accepted where it failed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Expected {
+    /// A version.
+    Version,
+    /// A number.
+    Number,
+    /// A wildcard, `*`, `x` or `X`.
+    Wildcard,
+    /// `.`.
+    Dot,
+    /// `-`.
+    Hyphen,
+    /// `+`.
+    Plus,
+    /// A pre-release or build identifier.
+    Identifier,
+    /// The end of the input.
+    End,
+}
+
+impl Display for Expected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let description = match self {
+            Expected::Version => "a version",
+            Expected::Number => "a number",
+            Expected::Wildcard => "a wildcard",
+            Expected::Dot => "`.`",
+            Expected::Hyphen => "`-`",
+            Expected::Plus => "`+`",
+            Expected::Identifier => "an identifier",
+            Expected::End => "the end of the input",
+        };
+        write!(f, "{}", description)
+    }
+}
+
+/// An error parsing a version or a range.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError {
+    kind: ErrorKind,
+    span: Span,
+    expected: Vec<Expected>,
+    input: String,
+}
+
+impl ParseError {
+    pub(crate) fn new(kind: ErrorKind, span: Span) -> Self {
+        ParseError {
+            kind,
+            span,
+            expected: vec![],
+            input: String::new(),
+        }
+    }
+
+    pub(crate) fn empty_input(input: &str) -> Self {
+        ParseError::new(ErrorKind::EmptyInput, Span::new(0, input.len())).with_input(input)
+    }
+
+    pub(crate) fn expecting(mut self, expected: &[Expected]) -> Self {
+        self.expected = expected.to_vec();
+        self
+    }
+
+    // Errors are raised deep in the parser, which only sees tokens; the
+    // input is attached on the way out for the messages to quote it.
+    pub(crate) fn with_input(mut self, input: &str) -> Self {
+        input.clone_into(&mut self.input);
+        self
+    }
+
+    /// What went wrong.
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+
+    /// Where in the input it went wrong.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// What would have been accepted instead.
+    pub fn expected(&self) -> &[Expected] {
+        &self.expected
+    }
+
+    /// The input that was parsed.
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+
+    /// The part of the input the error is about, empty at the end of input.
+    pub fn fragment(&self) -> &str {
+        self.input.get(self.span.start..self.span.end).unwrap_or("")
+    }
+
+    /// The message followed by the input with the offending part underlined:
+    ///
+    /// ```text
+    /// unexpected `..` at 3, expected a number
+    /// 1.2..3
+    ///    ^^
+    /// ```
+    pub fn snippet(&self) -> String {
+        let offset = self
+            .input
+            .get(..self.span.start)
+            .map_or(0, |prefix| prefix.chars().count());
+        let width = self.fragment().chars().count().max(1);
+
+        format!(
+            "{}\n{}\n{}{}",
+            self,
+            self.input,
+            " ".repeat(offset),
+            "^".repeat(width)
+        )
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.kind {
+            ErrorKind::EmptyInput => write!(f, "empty input")?,
+            ErrorKind::InvalidCharacter(c) => {
+                write!(f, "invalid character `{}` at {}", c, self.span.start)?
+            }
+            ErrorKind::UnexpectedToken => {
+                write!(f, "unexpected `{}` at {}", self.fragment(), self.span.start)?
+            }
+            ErrorKind::UnexpectedEnd => write!(f, "unexpected end of input")?,
+            ErrorKind::LeadingZero => write!(
+                f,
+                "numeric identifier `{}` at {} has a leading zero",
+                self.fragment(),
+                self.span.start
+            )?,
+            ErrorKind::Overflow => write!(
+                f,
+                "number `{}` at {} is larger than {}",
+                self.fragment(),
+                self.span.start,
+                u64::MAX
+            )?,
+        }
+
+        if let Some((last, rest)) = self.expected.split_last() {
+            write!(f, ", expected ")?;
+            for (i, expected) in rest.iter().enumerate() {
+                let separator = if i == 0 { "" } else { ", " };
+                write!(f, "{}{}", separator, expected)?;
+            }
+            if !rest.is_empty() {
+                write!(f, " or ")?;
+            }
+            write!(f, "{}", last)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rendering() {
+        let error = ParseError::new(ErrorKind::UnexpectedToken, Span::new(3, 5))
+            .expecting(&[Expected::Number, Expected::Wildcard])
+            .with_input("1.2..3");
+
+        assert_eq!(error.fragment(), "..");
+        assert_eq!(
+            error.to_string(),
+            "unexpected `..` at 3, expected a number or a wildcard"
+        );
+        assert_eq!(
+            error.snippet(),
+            "unexpected `..` at 3, expected a number or a wildcard\n1.2..3\n   ^^"
+        );
+
+        let error = ParseError::new(ErrorKind::UnexpectedToken, Span::new(5, 6))
+            .expecting(&[Expected::Hyphen, Expected::Plus, Expected::End])
+            .with_input("1.2.3x");
+        assert_eq!(
+            error.to_string(),
+            "unexpected `x` at 5, expected `-`, `+` or the end of the input"
+        );
+
+        let error = ParseError::new(ErrorKind::UnexpectedEnd, Span::at(6))
+            .expecting(&[Expected::Identifier])
+            .with_input("1.2.3-");
+        assert_eq!(error.fragment(), "");
+        assert_eq!(
+            error.snippet(),
+            "unexpected end of input, expected an identifier\n1.2.3-\n      ^"
+        );
+    }
+}
@@ -0,0 +1,403 @@
+use std::cmp::Ordering;
+
+use super::{
+    condition::{ComparatorSet, ConditionRange},
+    semver::Version,
+};
+
+// A position in between versions. Every interval is half-open over cuts, which
+// turns inclusive and exclusive comparators into the same kind of bound:
+// `>=v` starts at `Before(v)`, `>v` at `After(v)`, `<=v` ends at `After(v)`
+// and `<v` ends at `Before(v)`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Cut {
+    Start,
+    Before(Version),
+    After(Version),
+    End,
+}
+
+impl Ord for Cut {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Cut::Start, Cut::Start) | (Cut::End, Cut::End) => Ordering::Equal,
+            (Cut::Start, _) | (_, Cut::End) => Ordering::Less,
+            (_, Cut::Start) | (Cut::End, _) => Ordering::Greater,
+            (Cut::Before(l), Cut::Before(r)) | (Cut::After(l), Cut::After(r)) => l.cmp(r),
+            (Cut::Before(l), Cut::After(r)) => l.cmp(r).then(Ordering::Less),
+            (Cut::After(l), Cut::Before(r)) => l.cmp(r).then(Ordering::Greater),
+        }
+    }
+}
+
+impl PartialOrd for Cut {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Interval {
+    pub lower: Cut,
+    pub upper: Cut,
+}
+
+impl Interval {
+    pub fn from_comparators(set: &[ConditionRange]) -> Self {
+        let mut interval = Interval {
+            lower: Cut::Start,
+            upper: Cut::End,
+        };
+
+        for comparator in set {
+            let (lower, upper) = match comparator {
+                ConditionRange::GreaterEqual(v) => (Cut::Before(v.clone()), Cut::End),
+                ConditionRange::Greater(v) => (Cut::After(v.clone()), Cut::End),
+                ConditionRange::LessEqual(v) => (Cut::Start, Cut::After(v.clone())),
+                ConditionRange::Less(v) => (Cut::Start, Cut::Before(v.clone())),
+            };
+            interval = interval.intersect(&Interval { lower, upper });
+        }
+
+        interval
+    }
+
+    pub fn to_comparators(&self) -> ComparatorSet {
+        let lower = match &self.lower {
+            Cut::Before(v) => Some(ConditionRange::GreaterEqual(v.clone())),
+            Cut::After(v) => Some(ConditionRange::Greater(v.clone())),
+            Cut::Start | Cut::End => None,
+        };
+        let upper = match &self.upper {
+            Cut::Before(v) => Some(ConditionRange::Less(v.clone())),
+            Cut::After(v) => Some(ConditionRange::LessEqual(v.clone())),
+            Cut::Start | Cut::End => None,
+        };
+
+        lower.into_iter().chain(upper).collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lower >= self.upper
+    }
+
+    pub fn contains(&self, version: &Version) -> bool {
+        self.above_lower(version) && self.below_upper(version)
+    }
+
+    pub fn above_lower(&self, version: &Version) -> bool {
+        match &self.lower {
+            Cut::Start => true,
+            Cut::Before(v) => version >= v,
+            Cut::After(v) => version > v,
+            Cut::End => false,
+        }
+    }
+
+    pub fn below_upper(&self, version: &Version) -> bool {
+        match &self.upper {
+            Cut::Start => false,
+            Cut::Before(v) => version < v,
+            Cut::After(v) => version <= v,
+            Cut::End => true,
+        }
+    }
+
+    pub fn intersect(&self, other: &Interval) -> Interval {
+        Interval {
+            lower: self.lower.clone().max(other.lower.clone()),
+            upper: self.upper.clone().min(other.upper.clone()),
+        }
+    }
+}
+
+// Pre-releases only match a comparator set that names their exact tuple, so
+// the versions a set matches are split into one piece holding releases and
+// one piece per tuple whose pre-releases were opted into.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Kind {
+    Release,
+    PreRelease(u64, u64, u64),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Piece {
+    pub kind: Kind,
+    pub interval: Interval,
+}
+
+impl Piece {
+    pub fn to_comparators(&self) -> ComparatorSet {
+        let mut comparators = self.interval.to_comparators();
+        if self.kind == Kind::Release {
+            comparators.retain(|c| *c != ConditionRange::GreaterEqual(Version::default()));
+        }
+        comparators
+    }
+}
+
+pub fn pieces(set: &[ConditionRange]) -> Vec<Piece> {
+    let interval = Interval::from_comparators(set);
+    if interval.is_empty() {
+        return vec![];
+    }
+
+    let mut tuples = set
+        .iter()
+        .map(|c| c.version())
+        .filter(|v| !v.pre_release.is_empty())
+        .map(|v| (v.major, v.minor, v.patch))
+        .collect::<Vec<_>>();
+    tuples.sort();
+    tuples.dedup();
+
+    let releases = Piece {
+        kind: Kind::Release,
+        interval: Interval {
+            lower: release_cut(&interval.lower),
+            upper: release_cut(&interval.upper),
+        },
+    };
+
+    std::iter::once(releases)
+        .chain(tuples.into_iter().map(|(major, minor, patch)| {
+            let tuple = Interval {
+                lower: Cut::Before(Version {
+                    major,
+                    minor,
+                    patch,
+                    pre_release: ["0"].into_iter().collect(),
+                    ..Default::default()
+                }),
+                upper: Cut::Before(Version {
+                    major,
+                    minor,
+                    patch,
+                    ..Default::default()
+                }),
+            };
+            Piece {
+                kind: Kind::PreRelease(major, minor, patch),
+                interval: interval.intersect(&tuple),
+            }
+        }))
+        .filter(|p| !p.interval.is_empty())
+        .collect()
+}
+
+// Over releases alone every bound can be moved to the cut right before a
+// release, e.g. `>1.2.3` is `>=1.2.4` and `<=1.2.3-beta` is `<1.2.3`. With
+// that shape two release pieces match the same releases only when equal.
+fn release_cut(cut: &Cut) -> Cut {
+    match cut {
+        Cut::Start => Cut::Before(Version::default()),
+        Cut::End => Cut::End,
+        Cut::Before(v) => Cut::Before(release_of(v)),
+        Cut::After(v) if !v.pre_release.is_empty() => Cut::Before(release_of(v)),
+        Cut::After(v) => match successor(&[v.major, v.minor, v.patch]) {
+            Some((major, minor, patch)) => Cut::Before(Version {
+                major,
+                minor,
+                patch,
+                ..Default::default()
+            }),
+            None => Cut::End,
+        },
+    }
+}
+
+// The first release past every version that starts with `components`: `[1]`
+// is `2.0.0` and `[1, 2]` is `1.3.0`. A component at `u64::MAX` carries into
+// the one before it, so `[1, u64::MAX]` is `2.0.0`, and no release follows
+// once every component is at the maximum.
+pub fn successor(components: &[u64]) -> Option<(u64, u64, u64)> {
+    let mut next = [0; 3];
+    next[..components.len()].copy_from_slice(components);
+    for i in (0..components.len()).rev() {
+        match next[i].checked_add(1) {
+            Some(component) => {
+                next[i] = component;
+                return Some((next[0], next[1], next[2]));
+            }
+            None => next[i] = 0,
+        }
+    }
+
+    None
+}
+
+fn release_of(version: &Version) -> Version {
+    Version {
+        major: version.major,
+        minor: version.minor,
+        patch: version.patch,
+        ..Default::default()
+    }
+}
+
+pub fn intersect(left: &[Piece], right: &[Piece]) -> Vec<Piece> {
+    left.iter()
+        .flat_map(|l| {
+            right
+                .iter()
+                .filter(move |r| r.kind == l.kind)
+                .map(move |r| Piece {
+                    kind: l.kind,
+                    interval: l.interval.intersect(&r.interval),
+                })
+        })
+        .filter(|p| !p.interval.is_empty())
+        .collect()
+}
+
+// Merges the pieces of the same kind that overlap or touch each other.
+pub fn union(mut pieces: Vec<Piece>) -> Vec<Piece> {
+    pieces.sort_by(|l, r| {
+        l.kind
+            .cmp(&r.kind)
+            .then_with(|| l.interval.lower.cmp(&r.interval.lower))
+    });
+
+    let mut merged: Vec<Piece> = vec![];
+    for piece in pieces {
+        match merged.last_mut() {
+            Some(last)
+                if last.kind == piece.kind && piece.interval.lower <= last.interval.upper =>
+            {
+                if piece.interval.upper > last.interval.upper {
+                    last.interval.upper = piece.interval.upper;
+                }
+            }
+            _ => merged.push(piece),
+        }
+    }
+
+    merged
+}
+
+pub fn covers(pieces: &[Piece], piece: &Piece) -> bool {
+    let mut candidates = pieces
+        .iter()
+        .filter(|p| p.kind == piece.kind)
+        .map(|p| &p.interval)
+        .collect::<Vec<_>>();
+    candidates.sort_by(|l, r| l.lower.cmp(&r.lower));
+
+    let mut reached = piece.interval.lower.clone();
+    for interval in candidates {
+        if reached >= piece.interval.upper {
+            break;
+        }
+        if interval.lower <= reached && interval.upper > reached {
+            reached = interval.upper.clone();
+        }
+    }
+
+    reached >= piece.interval.upper
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(v: &str) -> Version {
+        Version::parse(v).unwrap()
+    }
+
+    #[test]
+    fn cut_order() {
+        let cuts = [
+            Cut::Start,
+            Cut::Before(version("1.0.0-0")),
+            Cut::After(version("1.0.0-0")),
+            Cut::Before(version("1.0.0")),
+            Cut::After(version("1.0.0")),
+            Cut::Before(version("1.0.1")),
+            Cut::End,
+        ];
+
+        for pair in cuts.windows(2) {
+            assert!(pair[0] < pair[1], "{:?} < {:?}", pair[0], pair[1]);
+        }
+    }
+
+    #[test]
+    fn release_pieces() {
+        let set = [
+            ConditionRange::Greater(version("1.2.3")),
+            ConditionRange::LessEqual(version("2.0.0-beta")),
+        ];
+        let pieces = pieces(&set);
+
+        assert_eq!(pieces.len(), 2);
+        assert_eq!(
+            pieces[0].interval,
+            Interval {
+                lower: Cut::Before(version("1.2.4")),
+                upper: Cut::Before(version("2.0.0")),
+            }
+        );
+        assert_eq!(pieces[1].kind, Kind::PreRelease(2, 0, 0));
+        assert!(pieces[1].interval.contains(&version("2.0.0-alpha")));
+        assert!(!pieces[1].interval.contains(&version("2.0.0-rc")));
+    }
+
+    #[test]
+    fn successors() {
+        assert_eq!(successor(&[1]), Some((2, 0, 0)));
+        assert_eq!(successor(&[1, 2]), Some((1, 3, 0)));
+        assert_eq!(successor(&[1, 2, 3]), Some((1, 2, 4)));
+        assert_eq!(successor(&[1, u64::MAX]), Some((2, 0, 0)));
+        assert_eq!(successor(&[0, 0, u64::MAX]), Some((0, 1, 0)));
+        assert_eq!(successor(&[u64::MAX]), None);
+        assert_eq!(successor(&[u64::MAX, u64::MAX, u64::MAX]), None);
+
+        let max = Version {
+            major: u64::MAX,
+            minor: u64::MAX,
+            patch: u64::MAX,
+            ..Default::default()
+        };
+        assert_eq!(release_cut(&Cut::After(max)), Cut::End);
+    }
+
+    #[test]
+    fn merging() {
+        let interval = |lower: &str, upper: &str| Piece {
+            kind: Kind::Release,
+            interval: Interval {
+                lower: Cut::Before(version(lower)),
+                upper: Cut::Before(version(upper)),
+            },
+        };
+
+        let merged = union(vec![
+            interval("1.5.0", "2.0.0"),
+            interval("3.0.0", "4.0.0"),
+            interval("1.0.0", "1.5.0"),
+            interval("1.2.0", "1.3.0"),
+        ]);
+        assert_eq!(
+            merged,
+            vec![interval("1.0.0", "2.0.0"), interval("3.0.0", "4.0.0")]
+        );
+    }
+
+    #[test]
+    fn coverage() {
+        let interval = |lower: &str, upper: &str| Piece {
+            kind: Kind::Release,
+            interval: Interval {
+                lower: Cut::Before(version(lower)),
+                upper: Cut::Before(version(upper)),
+            },
+        };
+
+        let cover = [interval("1.0.0", "1.5.0"), interval("1.5.0", "2.0.0")];
+        assert!(covers(&cover, &interval("1.2.0", "2.0.0")));
+        assert!(!covers(&cover, &interval("1.2.0", "2.0.1")));
+
+        let gap = [interval("1.0.0", "1.5.0"), interval("1.6.0", "2.0.0")];
+        assert!(!covers(&gap, &interval("1.2.0", "1.8.0")));
+    }
+}
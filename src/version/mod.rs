@@ -1,22 +1,20 @@
+//! Versions and version ranges as npm understands them. Both parse from
+//! strings, and parse errors point at the offending part of the input.
+
 pub mod condition;
 pub mod semver;
 
+mod error;
+mod interval;
 mod token;
 
-#[derive(Debug, PartialEq)]
-pub enum ParseError {
-    Unexpected,
-    EmptyInput,
-    EmptyTokenList,
-    InvalidToken(char),
-    InvalidTokenAt(usize),
-    MissingSymbolAt(usize),
-}
+pub use error::{ErrorKind, Expected, ParseError, Span};
 
-impl std::fmt::Display for ParseError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:?}", self)
-    }
+/// Loose parsing accepts what node-semver's loose mode does: any run of `v`,
+/// `=` and spaces before a version, leading zeros in its components and a
+/// pre-release glued to the patch, as in `1.2.3beta`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ParseOptions {
+    /// Accept the versions and ranges node-semver accepts in loose mode.
+    pub loose: bool,
 }
-
-impl std::error::Error for ParseError {}
@@ -1,15 +1,96 @@
+//! Single versions: parsing, precedence, incrementing and diffing.
+
+use std::cmp::Ordering;
 use std::fmt::Display;
+use std::ops::Index;
 
-use super::token::{tokenize, Token};
-use super::ParseError;
+use compact_str::CompactString;
+use smallvec::SmallVec;
 
-#[derive(Default, Debug, Clone, PartialEq)]
+use super::token::{tokenize, trim_spaces, Token, TokenKind};
+use super::{ErrorKind, Expected, ParseError, ParseOptions, Span};
+
+/// Pre-release and build identifiers. Most versions have at most two of each
+/// and they are short, so they usually live inline without an allocation.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct Identifiers(SmallVec<[CompactString; 2]>);
+
+impl Identifiers {
+    /// No identifiers.
+    pub fn new() -> Self {
+        Identifiers::default()
+    }
+
+    /// The number of identifiers.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Whether there are no identifiers.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The identifier at `index`.
+    pub fn get(&self, index: usize) -> Option<&str> {
+        self.0.get(index).map(CompactString::as_str)
+    }
+
+    /// The identifiers in order.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &str> + ExactSizeIterator {
+        self.0.iter().map(CompactString::as_str)
+    }
+
+    /// Adds an identifier at the end.
+    pub fn push(&mut self, identifier: &str) {
+        self.0.push(identifier.into());
+    }
+
+    /// Removes every identifier.
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+}
+
+impl Display for Identifiers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0.join("."))
+    }
+}
+
+impl<'a> FromIterator<&'a str> for Identifiers {
+    fn from_iter<I: IntoIterator<Item = &'a str>>(iter: I) -> Self {
+        Identifiers(iter.into_iter().map(CompactString::from).collect())
+    }
+}
+
+impl Index<usize> for Identifiers {
+    type Output = str;
+
+    fn index(&self, index: usize) -> &str {
+        &self.0[index]
+    }
+}
+
+impl<const N: usize> PartialEq<[&str; N]> for Identifiers {
+    fn eq(&self, other: &[&str; N]) -> bool {
+        self.iter().eq(other.iter().copied())
+    }
+}
+
+/// A version, `1.2.3-beta.1+build`.
+#[derive(Default, Debug, Clone)]
 pub struct Version {
-    pub major: u32,
-    pub minor: u32,
-    pub patch: u32,
-    pub pre_release: Vec<String>,
-    pub metadata: Vec<String>,
+    /// The major version.
+    pub major: u64,
+    /// The minor version.
+    pub minor: u64,
+    /// The patch version.
+    pub patch: u64,
+    /// The identifiers after the `-`, empty for a release.
+    pub pre_release: Identifiers,
+    /// The build identifiers after the `+`, ignored for precedence.
+    pub metadata: Identifiers,
 }
 
 impl Display for Version {
@@ -20,13 +101,13 @@ impl Display for Version {
             self.major,
             self.minor,
             self.patch,
-            if self.pre_release.len() > 0 {
-                format!("-{}", self.pre_release.join("."))
+            if !self.pre_release.is_empty() {
+                format!("-{}", self.pre_release)
             } else {
                 "".to_owned()
             },
-            if self.metadata.len() > 0 {
-                format!("-{}", self.metadata.join("."))
+            if !self.metadata.is_empty() {
+                format!("+{}", self.metadata)
             } else {
                 "".to_owned()
             },
@@ -35,150 +116,744 @@ impl Display for Version {
 }
 
 impl Version {
+    /// Parses a version, with an optional `v` or `=` in front.
+    pub fn parse(input: &str) -> Result<Self, ParseError> {
+        Version::parse_with_options(input, &ParseOptions::default())
+    }
+
+    /// Parses a version, loosely when `options` say so.
+    pub fn parse_with_options(input: &str, options: &ParseOptions) -> Result<Self, ParseError> {
+        if input.trim().is_empty() {
+            return Err(ParseError::empty_input(input));
+        }
+
+        let tokens = tokenize(input)?;
+        build_from_tokens(input, trim_spaces(&tokens), options).map_err(|e| e.with_input(input))
+    }
+
+    /// Only accepts the SemVer 2.0 grammar: no prefix or surrounding spaces,
+    /// all three components, ASCII identifiers and no leading zeros in numeric
+    /// pre-release identifiers.
+    pub fn parse_strict(input: &str) -> Result<Self, ParseError> {
+        if input.is_empty() {
+            return Err(ParseError::empty_input(input));
+        }
+
+        let tokens = tokenize(input)?;
+        build(input, &tokens, Grammar::Strict, &ParseOptions::default())
+            .map(PartialVersion::into_version)
+            .map_err(|e| e.with_input(input))
+    }
+
+    /// Picks the first thing that looks like a version out of arbitrary text,
+    /// like node-semver's `coerce`: `version 2` is `2.0.0` and `1.2.3.4` is
+    /// `1.2.3`. Runs of more than 16 digits are never taken as a component.
+    pub fn coerce(input: &str) -> Option<Version> {
+        const MAX_DIGITS: usize = 16;
+
+        let bytes = input.as_bytes();
+        let digits_at = |from: usize| {
+            bytes[from..]
+                .iter()
+                .take_while(|b| b.is_ascii_digit())
+                .count()
+        };
+
+        let mut start = 0;
+        while start < bytes.len() {
+            let digits = digits_at(start);
+            if digits == 0 || digits > MAX_DIGITS {
+                start += digits.max(1);
+                continue;
+            }
+
+            let mut components = vec![&input[start..start + digits]];
+            let mut end = start + digits;
+            while components.len() < 3 && bytes.get(end) == Some(&b'.') {
+                let digits = digits_at(end + 1);
+                if digits == 0 || digits > MAX_DIGITS {
+                    break;
+                }
+                components.push(&input[end + 1..end + 1 + digits]);
+                end += 1 + digits;
+            }
+
+            let mut components = components.iter().map(|c| c.parse::<u64>().ok());
+            return Some(Version {
+                major: components.next().flatten()?,
+                minor: components.next().unwrap_or(Some(0))?,
+                patch: components.next().unwrap_or(Some(0))?,
+                ..Default::default()
+            });
+        }
+
+        None
+    }
+
+    /// Bumps the version the way node-semver's `inc` does. The `pre*` release
+    /// types take an optional identifier that prefixes the numeric counter:
+    /// `1.2.3` with `PrePatch` and `rc` is `1.2.4-rc.0`, and `PreRelease` with
+    /// `rc` then gives `1.2.4-rc.1`.
+    pub fn inc(&mut self, release: ReleaseType, identifier: Option<&str>) -> Result<(), IncError> {
+        if let Some(identifier) = identifier.filter(|_| release.is_pre()) {
+            if !is_valid_pre_release(identifier) {
+                return Err(IncError::InvalidIdentifier(identifier.to_owned()));
+            }
+        }
+
+        match release {
+            ReleaseType::PreMajor => {
+                self.major = increment(self.major)?;
+                self.pre_release.clear();
+                self.minor = 0;
+                self.patch = 0;
+                self.increment_pre_release(identifier);
+            }
+            ReleaseType::PreMinor => {
+                self.minor = increment(self.minor)?;
+                self.pre_release.clear();
+                self.patch = 0;
+                self.increment_pre_release(identifier);
+            }
+            ReleaseType::PrePatch => {
+                self.patch = increment(self.patch)?;
+                self.pre_release.clear();
+                self.increment_pre_release(identifier);
+            }
+            ReleaseType::PreRelease => {
+                if self.pre_release.is_empty() {
+                    self.inc(ReleaseType::Patch, None)?;
+                }
+                self.increment_pre_release(identifier);
+            }
+            ReleaseType::Release => {
+                if self.pre_release.is_empty() {
+                    return Err(IncError::NotPreRelease);
+                }
+                self.pre_release.clear();
+            }
+            // A pre-release of `2.0.0` becomes `2.0.0` itself, anything else
+            // moves on to the next major. Minor and patch work the same way.
+            ReleaseType::Major => {
+                if self.minor != 0 || self.patch != 0 || self.pre_release.is_empty() {
+                    self.major = increment(self.major)?;
+                }
+                self.minor = 0;
+                self.patch = 0;
+                self.pre_release.clear();
+            }
+            ReleaseType::Minor => {
+                if self.patch != 0 || self.pre_release.is_empty() {
+                    self.minor = increment(self.minor)?;
+                }
+                self.patch = 0;
+                self.pre_release.clear();
+            }
+            ReleaseType::Patch => {
+                if self.pre_release.is_empty() {
+                    self.patch = increment(self.patch)?;
+                }
+                self.pre_release.clear();
+            }
+        }
+
+        self.metadata.clear();
+        Ok(())
+    }
+
+    /// The kind of release that separates two versions, like node-semver's
+    /// `diff`. Leaving a pre-release for a release is classified by what the
+    /// pre-release was leading up to: `1.0.0-1` to `1.0.0` is a major change.
+    pub fn diff(&self, other: &Version) -> Option<ReleaseType> {
+        let (low, high) = match self.cmp(other) {
+            Ordering::Equal => return None,
+            Ordering::Less => (self, other),
+            Ordering::Greater => (other, self),
+        };
+        let same_core = (low.major, low.minor, low.patch) == (high.major, high.minor, high.patch);
+
+        if !low.pre_release.is_empty() && high.pre_release.is_empty() {
+            if low.minor == 0 && low.patch == 0 {
+                return Some(ReleaseType::Major);
+            }
+            if same_core {
+                return Some(if low.patch == 0 {
+                    ReleaseType::Minor
+                } else {
+                    ReleaseType::Patch
+                });
+            }
+        }
+
+        let (release, pre_release) = if low.major != high.major {
+            (ReleaseType::Major, ReleaseType::PreMajor)
+        } else if low.minor != high.minor {
+            (ReleaseType::Minor, ReleaseType::PreMinor)
+        } else if low.patch != high.patch {
+            (ReleaseType::Patch, ReleaseType::PrePatch)
+        } else {
+            return Some(ReleaseType::PreRelease);
+        };
+
+        if high.pre_release.is_empty() {
+            Some(release)
+        } else {
+            Some(pre_release)
+        }
+    }
+
+    // Bumps the last numeric identifier, appending a `0` counter when there
+    // is none. A different identifier restarts the counter under it.
+    fn increment_pre_release(&mut self, identifier: Option<&str>) {
+        match self.pre_release.0.iter_mut().rev().find(|i| is_numeric(i)) {
+            Some(counter) => *counter = increment_numeric(counter),
+            None => self.pre_release.push("0"),
+        }
+
+        if let Some(identifier) = identifier {
+            let prefix = identifier.split('.').collect::<Identifiers>();
+            let keeps_counter = self.pre_release.0.starts_with(&prefix.0)
+                && self.pre_release.get(prefix.len()).is_some_and(is_numeric);
+
+            if !keeps_counter {
+                self.pre_release = prefix;
+                self.pre_release.push("0");
+            }
+        }
+    }
+}
+
+impl PartialEq for Version {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Version {}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// SemVer 2.0 precedence (§11): build metadata never takes part in it.
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.major
+            .cmp(&other.major)
+            .then(self.minor.cmp(&other.minor))
+            .then(self.patch.cmp(&other.patch))
+            .then_with(|| compare_pre_release(&self.pre_release.0, &other.pre_release.0))
+    }
+}
+
+fn compare_pre_release(left: &[CompactString], right: &[CompactString]) -> Ordering {
+    match (left.is_empty(), right.is_empty()) {
+        (true, true) => Ordering::Equal,
+        (true, false) => Ordering::Greater,
+        (false, true) => Ordering::Less,
+        (false, false) => left
+            .iter()
+            .zip(right)
+            .map(|(l, r)| compare_identifier(l, r))
+            .find(|o| o.is_ne())
+            .unwrap_or_else(|| left.len().cmp(&right.len())),
+    }
+}
+
+fn compare_identifier(left: &str, right: &str) -> Ordering {
+    match (is_numeric(left), is_numeric(right)) {
+        (true, true) => {
+            let left = left.trim_start_matches('0');
+            let right = right.trim_start_matches('0');
+            left.len().cmp(&right.len()).then_with(|| left.cmp(right))
+        }
+        (true, false) => Ordering::Less,
+        (false, true) => Ordering::Greater,
+        (false, false) => left.cmp(right),
+    }
+}
+
+fn is_numeric(identifier: &str) -> bool {
+    identifier.bytes().all(|b| b.is_ascii_digit())
+}
+
+fn increment(component: u64) -> Result<u64, IncError> {
+    component.checked_add(1).ok_or(IncError::Overflow)
+}
+
+fn increment_numeric(identifier: &str) -> CompactString {
+    let mut digits = identifier.trim_start_matches('0').as_bytes().to_vec();
+    match digits.iter().rposition(|&b| b != b'9') {
+        Some(i) => {
+            digits[i] += 1;
+            digits[i + 1..].fill(b'0');
+        }
+        None => {
+            digits.fill(b'0');
+            digits.insert(0, b'1');
+        }
+    }
+
+    digits.into_iter().map(char::from).collect()
+}
+
+fn is_valid_pre_release(identifier: &str) -> bool {
+    identifier.split('.').all(|part| {
+        !part.is_empty()
+            && part.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-')
+            && !(part.len() > 1 && part.starts_with('0') && is_numeric(part))
+    })
+}
+
+/// The kinds of release [`Version::inc`] bumps to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReleaseType {
+    /// `1.2.3` to `2.0.0`.
+    Major,
+    /// `1.2.3` to `2.0.0-0`.
+    PreMajor,
+    /// `1.2.3` to `1.3.0`.
+    Minor,
+    /// `1.2.3` to `1.3.0-0`.
+    PreMinor,
+    /// `1.2.3` to `1.2.4`.
+    Patch,
+    /// `1.2.3` to `1.2.4-0`.
+    PrePatch,
+    /// `1.2.3-0` to `1.2.3-1`, and `1.2.3` to `1.2.4-0`.
+    PreRelease,
+    /// `1.2.3-0` to `1.2.3`.
+    Release,
+}
+
+impl ReleaseType {
+    fn is_pre(&self) -> bool {
+        matches!(
+            self,
+            ReleaseType::PreMajor
+                | ReleaseType::PreMinor
+                | ReleaseType::PrePatch
+                | ReleaseType::PreRelease
+        )
+    }
+}
+
+impl Display for ReleaseType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ReleaseType::Major => "major",
+            ReleaseType::PreMajor => "premajor",
+            ReleaseType::Minor => "minor",
+            ReleaseType::PreMinor => "preminor",
+            ReleaseType::Patch => "patch",
+            ReleaseType::PrePatch => "prepatch",
+            ReleaseType::PreRelease => "prerelease",
+            ReleaseType::Release => "release",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Why a version could not be bumped.
+#[derive(Debug, PartialEq)]
+pub enum IncError {
+    /// The pre-release identifier given is not a valid one.
+    InvalidIdentifier(String),
+    /// `Release` was asked of a version that is already a release.
+    NotPreRelease,
+    /// A component would go past `u64::MAX`.
+    Overflow,
+}
+
+impl Display for IncError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::error::Error for IncError {}
+
+/// A version that may lack components, as in `1.2`, `1.x` or `*`.
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct PartialVersion {
+    /// The major version, `None` for a wildcard.
+    pub major: Option<u64>,
+    /// The minor version, `None` when missing or a wildcard.
+    pub minor: Option<u64>,
+    /// The patch version, `None` when missing or a wildcard.
+    pub patch: Option<u64>,
+    /// The identifiers after the `-`.
+    pub pre_release: Identifiers,
+    /// The build identifiers after the `+`.
+    pub metadata: Identifiers,
+}
+
+impl Display for PartialVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // A wildcard major is written out, or the version would print as
+        // nothing at all; later wildcards are left off.
+        let core = match self.major {
+            None => "*".to_owned(),
+            Some(_) => [self.major, self.minor, self.patch]
+                .iter()
+                .map_while(|c| c.map(|c| c.to_string()))
+                .collect::<Vec<_>>()
+                .join("."),
+        };
+
+        write!(
+            f,
+            "{}{}{}",
+            core,
+            if !self.pre_release.is_empty() {
+                format!("-{}", self.pre_release)
+            } else {
+                "".to_owned()
+            },
+            if !self.metadata.is_empty() {
+                format!("+{}", self.metadata)
+            } else {
+                "".to_owned()
+            },
+        )
+    }
+}
+
+impl PartialVersion {
+    /// Parses a version that may have wildcards or missing components.
     pub fn parse(input: &str) -> Result<Self, ParseError> {
-        let input = input.trim();
+        PartialVersion::parse_with_options(input, &ParseOptions::default())
+    }
 
-        if input.len() == 0 {
-            return Err(ParseError::EmptyInput);
+    /// Parses a partial version, loosely when `options` say so.
+    pub fn parse_with_options(input: &str, options: &ParseOptions) -> Result<Self, ParseError> {
+        if input.trim().is_empty() {
+            return Err(ParseError::empty_input(input));
         }
 
         let tokens = tokenize(input)?;
-        build_from_tokens(&tokens)
+        build_partial_from_tokens(input, trim_spaces(&tokens), options)
+            .map_err(|e| e.with_input(input))
     }
 
-    pub fn get_version_offset(&self) -> i64 {
-        let mj = self.major as i64 * 1_000_000;
-        let mn = self.minor as i64 * 10_000;
-        let p = self.patch as i64;
-        mj + mn + p
+    /// Omitted components are filled with zeros, e.g. `1.2` becomes `1.2.0`.
+    pub fn to_version(&self) -> Version {
+        Version {
+            major: self.major.unwrap_or_default(),
+            minor: self.minor.unwrap_or_default(),
+            patch: self.patch.unwrap_or_default(),
+            pre_release: self.pre_release.clone(),
+            metadata: self.metadata.clone(),
+        }
+    }
+
+    /// Like [`PartialVersion::to_version`], without cloning.
+    pub fn into_version(self) -> Version {
+        Version {
+            major: self.major.unwrap_or_default(),
+            minor: self.minor.unwrap_or_default(),
+            patch: self.patch.unwrap_or_default(),
+            pre_release: self.pre_release,
+            metadata: self.metadata,
+        }
+    }
+
+    /// Whether any component is missing.
+    pub fn is_partial(&self) -> bool {
+        self.major.is_none() || self.minor.is_none() || self.patch.is_none()
+    }
+}
+
+impl From<Version> for PartialVersion {
+    fn from(version: Version) -> Self {
+        PartialVersion {
+            major: Some(version.major),
+            minor: Some(version.minor),
+            patch: Some(version.patch),
+            pre_release: version.pre_release,
+            metadata: version.metadata,
+        }
     }
 }
 
 #[derive(Default)]
 struct VersionBuilder {
-    major: Option<u32>,
-    minor: Option<u32>,
-    patch: Option<u32>,
-    pre_release: Vec<String>,
-    metadata: Vec<String>,
+    strict: bool,
+    major: Option<u64>,
+    minor: Option<u64>,
+    patch: Option<u64>,
+    components: usize,
+    wildcard: bool,
+    pre_release: Identifiers,
+    metadata: Identifiers,
+}
+
+impl VersionBuilder {
+    // Once a component is a wildcard every following one is too: `1.x.3` is `1.x`.
+    fn push_component(&mut self, value: Option<u64>) {
+        let value = value.filter(|_| !self.wildcard);
+        self.wildcard |= value.is_none();
+
+        match self.components {
+            0 => self.major = value,
+            1 => self.minor = value,
+            _ => self.patch = value,
+        }
+        self.components += 1;
+    }
+
+    fn push_identifier(
+        &mut self,
+        state: &ParsingState,
+        input: &str,
+        span: Span,
+    ) -> Result<(), ParseError> {
+        let identifier = &input[span.start..span.end];
+        match state {
+            ParsingState::Core => (),
+            ParsingState::PreRelease => {
+                let leading_zero = identifier.len() > 1 && identifier.starts_with('0');
+                if self.strict && leading_zero && is_numeric(identifier) {
+                    return Err(ParseError::new(ErrorKind::LeadingZero, span));
+                }
+                self.pre_release.push(identifier)
+            }
+            ParsingState::Metadata => self.metadata.push(identifier),
+        }
+        Ok(())
+    }
+}
+
+fn is_wildcard(token: &TokenKind) -> bool {
+    match token {
+        TokenKind::Asterisk => true,
+        TokenKind::AlphaNumeric(s) => *s == "x" || *s == "X",
+        _ => false,
+    }
+}
+
+fn is_v(token: &Token) -> bool {
+    token.kind == TokenKind::AlphaNumeric("v")
+}
+
+// By default a version may be preceded by `=` then `v`, loosely by any mix of
+// them and spaces and strictly by nothing.
+fn strip_prefix<'t, 'a>(
+    tokens: &'t [Token<'a>],
+    grammar: Grammar,
+    options: &ParseOptions,
+) -> &'t [Token<'a>] {
+    if grammar == Grammar::Strict {
+        return tokens;
+    }
+    if options.loose {
+        let prefix = tokens
+            .iter()
+            .take_while(|t| matches!(t.kind, TokenKind::Space | TokenKind::Equal) || is_v(t))
+            .count();
+        return &tokens[prefix..];
+    }
+
+    let tokens = match tokens {
+        [equal, rest @ ..] if equal.kind == TokenKind::Equal => rest,
+        _ => tokens,
+    };
+    match tokens {
+        [v, rest @ ..] if is_v(v) => rest,
+        _ => tokens,
+    }
+}
+
+// Tokens only carry the text of single identifiers, so the input they were
+// read from is passed along to copy the pre-release and metadata out of it.
+fn build_from_tokens(
+    input: &str,
+    tokens: &[Token],
+    options: &ParseOptions,
+) -> Result<Version, ParseError> {
+    build(input, tokens, Grammar::Full, options).map(PartialVersion::into_version)
+}
+
+pub(crate) fn build_partial_from_tokens(
+    input: &str,
+    tokens: &[Token],
+    options: &ParseOptions,
+) -> Result<PartialVersion, ParseError> {
+    build(input, tokens, Grammar::Partial, options)
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Grammar {
+    // Components may be wildcards or left out, as in `1.x` or `1.2`.
+    Partial,
+    Full,
+    Strict,
 }
 
-pub fn build_from_tokens(tokens: &[Token]) -> Result<Version, ParseError> {
-    if tokens.len() == 0 {
-        return Err(ParseError::EmptyTokenList);
+// What may follow `prev` in the core of a version.
+fn expected_in_core(prev: &TokenKind, components: usize, grammar: Grammar) -> &'static [Expected] {
+    match (prev, grammar) {
+        (TokenKind::Empty | TokenKind::Dot, Grammar::Partial) => {
+            &[Expected::Number, Expected::Wildcard]
+        }
+        (TokenKind::Empty | TokenKind::Dot, _) => &[Expected::Number],
+        _ if components == 3 => &[Expected::Hyphen, Expected::Plus, Expected::End],
+        (_, Grammar::Strict) => &[Expected::Dot],
+        _ => &[
+            Expected::Dot,
+            Expected::Hyphen,
+            Expected::Plus,
+            Expected::End,
+        ],
     }
+}
 
-    let mut version = VersionBuilder::default();
-    let empty_token = Token::Empty;
+fn build(
+    input: &str,
+    tokens: &[Token],
+    grammar: Grammar,
+    options: &ParseOptions,
+) -> Result<PartialVersion, ParseError> {
+    let end = tokens.last().map_or(0, |t| t.span.end);
+    let tokens = strip_prefix(tokens, grammar, options);
+    if tokens.is_empty() {
+        return Err(ParseError::new(ErrorKind::UnexpectedEnd, Span::at(end))
+            .expecting(&[Expected::Version]));
+    }
+
+    let mut version = VersionBuilder {
+        strict: grammar == Grammar::Strict,
+        ..Default::default()
+    };
     let mut state = ParsingState::Core;
-    let mut prev = &empty_token;
-    let mut hyphen_accumulator = String::new();
+    let mut prev = &TokenKind::Empty;
+    // Identifiers may contain hyphens, so `beta-2` arrives as three tokens.
+    // Only the span they cover is tracked until the next dot.
+    let mut identifier: Option<Span> = None;
 
-    for (i, curr) in tokens.iter().enumerate() {
+    for curr in tokens {
         let mut change_to = None;
+        let unexpected = || ParseError::new(ErrorKind::UnexpectedToken, curr.span);
+
+        let non_ascii = match &curr.kind {
+            TokenKind::AlphaNumeric(s) if version.strict => {
+                s.char_indices().find(|(_, c)| !c.is_ascii())
+            }
+            _ => None,
+        };
+        if let Some((i, c)) = non_ascii {
+            let start = curr.span.start + i;
+            let span = Span::new(start, start + c.len_utf8());
+            return Err(ParseError::new(ErrorKind::InvalidCharacter(c), span));
+        }
 
         match state {
-            ParsingState::Core => match curr {
-                Token::Dot => match prev {
-                    Token::Number(_) if version.patch.is_none() => (),
-                    _ => return Err(ParseError::InvalidTokenAt(i)),
-                },
-
-                Token::Number(n) => match prev {
-                    Token::Empty => version.major = Some(*n),
-                    Token::Dot if version.minor.is_none() => version.minor = Some(*n),
-                    Token::Dot if version.patch.is_none() => version.patch = Some(*n),
-                    _ => return Err(ParseError::InvalidTokenAt(i)),
-                },
-
-                Token::Hyphen if *prev != Token::Dot => change_to = Some(ParsingState::PreRelease),
-                Token::Plus if *prev != Token::Dot => change_to = Some(ParsingState::Metadata),
-
-                _ => return Err(ParseError::InvalidTokenAt(i)),
-            },
-            ParsingState::PreRelease => match curr {
-                Token::Dot => match prev {
-                    Token::AlphaNumeric(_) | Token::Number(_) => (),
-                    Token::Hyphen => {
-                        version.pre_release.push(hyphen_accumulator.clone());
-                        hyphen_accumulator = String::new();
+            ParsingState::Core => {
+                let expected = expected_in_core(prev, version.components, grammar);
+                let is_component =
+                    matches!(prev, TokenKind::Empty | TokenKind::Dot) && version.components < 3;
+                let ends_core = !matches!(prev, TokenKind::Empty | TokenKind::Dot)
+                    && (grammar != Grammar::Strict || version.components == 3);
+
+                match &curr.kind {
+                    TokenKind::Dot if !is_component && version.components < 3 => (),
+
+                    TokenKind::Number(n) if is_component => version.push_component(Some(*n)),
+                    t if grammar == Grammar::Partial && is_wildcard(t) && is_component => {
+                        version.push_component(None)
                     }
-                    _ => return Err(ParseError::InvalidTokenAt(i)),
-                },
-                Token::Hyphen if *prev == Token::Dot || *prev == Token::Hyphen => {
-                    hyphen_accumulator.push('-');
-                }
-                Token::AlphaNumeric(identifier)
-                    if *prev == Token::Empty || *prev == Token::Dot || *prev == Token::Hyphen =>
-                {
-                    version.pre_release.push(format!(
-                        "{}{}",
-                        hyphen_accumulator,
-                        identifier.clone()
-                    ));
-                    hyphen_accumulator = String::new();
-                }
-                Token::Number(number)
-                    if *prev == Token::Empty || *prev == Token::Dot || *prev == Token::Hyphen =>
-                {
-                    version
-                        .pre_release
-                        .push(format!("{}{}", hyphen_accumulator, number,));
-                    hyphen_accumulator = String::new();
-                }
+                    // The tokenizer leaves numbers that do not fit as text.
+                    TokenKind::AlphaNumeric(s)
+                        if is_component && is_numeric(s) && !s.starts_with('0') =>
+                    {
+                        return Err(ParseError::new(ErrorKind::Overflow, curr.span))
+                    }
+                    // Loose components may have leading zeros, and the last one
+                    // may run straight into a pre-release: `1.02.3beta`.
+                    TokenKind::AlphaNumeric(s)
+                        if options.loose
+                            && is_component
+                            && s.starts_with(|c: char| c.is_ascii_digit()) =>
+                    {
+                        let digits = s.bytes().take_while(u8::is_ascii_digit).count();
+                        let number = s[..digits].parse().map_err(|_| {
+                            let span = Span::new(curr.span.start, curr.span.start + digits);
+                            ParseError::new(ErrorKind::Overflow, span)
+                        })?;
+                        version.push_component(Some(number));
 
-                Token::Plus if *prev != Token::Dot => change_to = Some(ParsingState::Metadata),
-                _ => return Err(ParseError::InvalidTokenAt(i)),
-            },
-            ParsingState::Metadata => match curr {
-                Token::Dot => match prev {
-                    Token::AlphaNumeric(_) | Token::Number(_) => (),
-                    Token::Hyphen => {
-                        version.metadata.push(hyphen_accumulator.clone());
-                        hyphen_accumulator = String::new();
+                        if digits < s.len() {
+                            identifier = Some(Span::new(curr.span.start + digits, curr.span.end));
+                            change_to = Some(ParsingState::PreRelease);
+                        }
                     }
-                    _ => return Err(ParseError::InvalidTokenAt(i)),
-                },
-                Token::Hyphen if *prev == Token::Dot || *prev == Token::Hyphen => {
-                    hyphen_accumulator.push('-');
-                }
-                Token::AlphaNumeric(identifier)
-                    if *prev == Token::Empty || *prev == Token::Dot || *prev == Token::Hyphen =>
-                {
-                    version
-                        .metadata
-                        .push(format!("{}{}", hyphen_accumulator, identifier.clone()));
-                    hyphen_accumulator = String::new();
-                }
-                Token::Number(number)
-                    if *prev == Token::Empty || *prev == Token::Dot || *prev == Token::Hyphen =>
-                {
-                    version
-                        .metadata
-                        .push(format!("{}{}", hyphen_accumulator, number,));
-                    hyphen_accumulator = String::new();
+
+                    TokenKind::Hyphen if ends_core => change_to = Some(ParsingState::PreRelease),
+                    TokenKind::Plus if ends_core => change_to = Some(ParsingState::Metadata),
+
+                    _ => return Err(unexpected().expecting(expected)),
                 }
+            }
+            ParsingState::PreRelease | ParsingState::Metadata => {
+                let expected: &[Expected] = match state {
+                    _ if identifier.is_none() => &[Expected::Identifier],
+                    ParsingState::PreRelease => &[Expected::Dot, Expected::Plus, Expected::End],
+                    _ => &[Expected::Dot, Expected::End],
+                };
+                let ends_identifier = match &curr.kind {
+                    TokenKind::Dot => true,
+                    TokenKind::Plus if matches!(state, ParsingState::PreRelease) => true,
+                    TokenKind::Hyphen | TokenKind::AlphaNumeric(_) | TokenKind::Number(_) => {
+                        identifier = Some(
+                            identifier
+                                .map_or(curr.span, |span| Span::new(span.start, curr.span.end)),
+                        );
+                        false
+                    }
+                    _ => return Err(unexpected().expecting(expected)),
+                };
 
-                _ => return Err(ParseError::InvalidTokenAt(i)),
-            },
+                if ends_identifier {
+                    let Some(span) = identifier.take() else {
+                        return Err(unexpected().expecting(expected));
+                    };
+                    version.push_identifier(&state, input, span)?;
+                    if curr.kind == TokenKind::Plus {
+                        change_to = Some(ParsingState::Metadata);
+                    }
+                }
+            }
         }
 
         if let Some(change_to) = change_to {
-            prev = &empty_token;
+            prev = &TokenKind::Empty;
             state = change_to;
         } else {
-            prev = curr;
+            prev = &curr.kind;
         }
     }
 
-    let major = version.major.ok_or(ParseError::MissingSymbolAt(0))?;
-    let minor = version.minor.unwrap_or_default();
-    let patch = version.patch.unwrap_or_default();
+    let at_end = ParseError::new(ErrorKind::UnexpectedEnd, Span::at(end));
+    match state {
+        ParsingState::Core if matches!(prev, TokenKind::Dot) || version.components == 0 => {
+            return Err(at_end.expecting(expected_in_core(prev, version.components, grammar)));
+        }
+        ParsingState::Core if version.strict && version.components < 3 => {
+            return Err(at_end.expecting(&[Expected::Dot]));
+        }
+        ParsingState::Core => (),
+        _ => match identifier {
+            Some(span) => version.push_identifier(&state, input, span)?,
+            None => return Err(at_end.expecting(&[Expected::Identifier])),
+        },
+    }
 
-    Ok(Version {
-        major,
-        minor,
-        patch,
+    Ok(PartialVersion {
+        major: version.major,
+        minor: version.minor,
+        patch: version.patch,
         pre_release: version.pre_release,
         metadata: version.metadata,
     })
@@ -233,24 +908,29 @@ mod tests {
         assert_eq!(version.patch, 196);
 
         let v = ".1.1";
-        let version = Version::parse(v).unwrap_err();
-        assert_eq!(version, ParseError::InvalidTokenAt(0));
+        let error = Version::parse(v).unwrap_err();
+        assert_eq!(error.kind(), &ErrorKind::UnexpectedToken);
+        assert_eq!(error.span(), Span::new(0, 1));
 
         let v = "..1";
-        let version = Version::parse(v).unwrap_err();
-        assert_eq!(version, ParseError::InvalidTokenAt(0));
+        let error = Version::parse(v).unwrap_err();
+        assert_eq!(error.kind(), &ErrorKind::UnexpectedToken);
+        assert_eq!(error.span(), Span::new(0, 1));
 
         let v = "1..";
-        let version = Version::parse(v).unwrap_err();
-        assert_eq!(version, ParseError::InvalidTokenAt(2));
+        let error = Version::parse(v).unwrap_err();
+        assert_eq!(error.kind(), &ErrorKind::UnexpectedToken);
+        assert_eq!(error.span(), Span::new(2, 3));
 
         let v = "1.0.0.";
-        let version = Version::parse(v).unwrap_err();
-        assert_eq!(version, ParseError::InvalidTokenAt(5));
+        let error = Version::parse(v).unwrap_err();
+        assert_eq!(error.kind(), &ErrorKind::UnexpectedToken);
+        assert_eq!(error.span(), Span::new(5, 6));
 
         let v = "1.0.0.12";
-        let version = Version::parse(v).unwrap_err();
-        assert_eq!(version, ParseError::InvalidTokenAt(5));
+        let error = Version::parse(v).unwrap_err();
+        assert_eq!(error.kind(), &ErrorKind::UnexpectedToken);
+        assert_eq!(error.span(), Span::new(5, 6));
     }
 
     #[test]
@@ -286,12 +966,14 @@ mod tests {
         assert_eq!(version.pre_release[5], "th3t4".to_owned());
 
         let v = "50-.beta.--.omega.123.th3t4";
-        let version = Version::parse(v).unwrap_err();
-        assert_eq!(version, ParseError::InvalidTokenAt(2));
+        let error = Version::parse(v).unwrap_err();
+        assert_eq!(error.kind(), &ErrorKind::UnexpectedToken);
+        assert_eq!(error.span(), Span::new(3, 4));
 
         let v = "1.0.0-rc..1";
-        let version = Version::parse(v).unwrap_err();
-        assert_eq!(version, ParseError::InvalidTokenAt(8));
+        let error = Version::parse(v).unwrap_err();
+        assert_eq!(error.kind(), &ErrorKind::UnexpectedToken);
+        assert_eq!(error.span(), Span::new(9, 10));
     }
 
     #[test]
@@ -335,11 +1017,522 @@ mod tests {
         assert_eq!(version.metadata[5], "th3t4".to_owned());
 
         let v = "50+.beta.--.omega.123.th3t4";
-        let version = Version::parse(v).unwrap_err();
-        assert_eq!(version, ParseError::InvalidTokenAt(2));
+        let error = Version::parse(v).unwrap_err();
+        assert_eq!(error.kind(), &ErrorKind::UnexpectedToken);
+        assert_eq!(error.span(), Span::new(3, 4));
 
         let v = "1.0.0+rc..1";
-        let version = Version::parse(v).unwrap_err();
-        assert_eq!(version, ParseError::InvalidTokenAt(8));
+        let error = Version::parse(v).unwrap_err();
+        assert_eq!(error.kind(), &ErrorKind::UnexpectedToken);
+        assert_eq!(error.span(), Span::new(9, 10));
+    }
+
+    #[test]
+    fn precedence() {
+        let ordered = [
+            "1.0.0-alpha",
+            "1.0.0-alpha.1",
+            "1.0.0-alpha.beta",
+            "1.0.0-beta",
+            "1.0.0-beta.2",
+            "1.0.0-beta.11",
+            "1.0.0-rc.1",
+            "1.0.0",
+            "1.0.1",
+            "1.1.0",
+            "1.10000.0",
+            "2.0.0",
+        ];
+
+        for pair in ordered.windows(2) {
+            let lower = Version::parse(pair[0]).unwrap();
+            let higher = Version::parse(pair[1]).unwrap();
+            assert!(lower < higher, "{} < {}", pair[0], pair[1]);
+            assert!(higher > lower, "{} > {}", pair[1], pair[0]);
+        }
+
+        let v1 = Version::parse("1.10000.0").unwrap();
+        let v2 = Version::parse("2.0.0").unwrap();
+        assert_ne!(v1, v2);
+
+        let v1 = Version::parse("1.0.0-alpha+build.1").unwrap();
+        let v2 = Version::parse("1.0.0-alpha+exp.sha.5114f85").unwrap();
+        assert_eq!(v1, v2);
+        assert_eq!(v1.cmp(&v2), Ordering::Equal);
+
+        let v1 = Version::parse("1.0.0-2").unwrap();
+        let v2 = Version::parse("1.0.0-10").unwrap();
+        assert!(v1 < v2);
+
+        let v1 = Version::parse("1.0.0-10").unwrap();
+        let v2 = Version::parse("1.0.0-1a").unwrap();
+        assert!(v1 < v2);
+    }
+
+    #[test]
+    fn partial() {
+        let version = PartialVersion::parse("1.2").unwrap();
+        assert_eq!(version.major, Some(1));
+        assert_eq!(version.minor, Some(2));
+        assert_eq!(version.patch, None);
+        assert!(version.is_partial());
+        assert_eq!(version.to_string(), "1.2");
+        assert_eq!(version.to_version(), Version::parse("1.2.0").unwrap());
+
+        let version = PartialVersion::parse("3-beta.1+build").unwrap();
+        assert_eq!(version.major, Some(3));
+        assert_eq!(version.minor, None);
+        assert_eq!(version.pre_release, ["beta", "1"]);
+        assert_eq!(version.to_string(), "3-beta.1+build");
+
+        let version = PartialVersion::parse("1.2.3").unwrap();
+        assert!(!version.is_partial());
+    }
+
+    #[test]
+    fn wildcards() {
+        for v in ["1.x", "1.X", "1.*", "1.x.x", "1.x.3"] {
+            let version = PartialVersion::parse(v).unwrap();
+            assert_eq!(version.major, Some(1));
+            assert_eq!(version.minor, None);
+            assert_eq!(version.patch, None);
+        }
+
+        let version = PartialVersion::parse("1.2.*").unwrap();
+        assert_eq!(version.minor, Some(2));
+        assert_eq!(version.patch, None);
+
+        let version = PartialVersion::parse("x").unwrap();
+        assert_eq!(version.major, None);
+
+        let v = "1.x";
+        let error = Version::parse(v).unwrap_err();
+        assert_eq!(error.kind(), &ErrorKind::UnexpectedToken);
+        assert_eq!(error.span(), Span::new(2, 3));
+
+        let v = "1.x..";
+        let error = PartialVersion::parse(v).unwrap_err();
+        assert_eq!(error.kind(), &ErrorKind::UnexpectedToken);
+        assert_eq!(error.span(), Span::new(4, 5));
+    }
+
+    #[test]
+    fn display() {
+        for v in [
+            "1.2.3",
+            "1.0.0-alpha.1",
+            "1.0.0+build.5",
+            "1.0.0-rc.1+build.5",
+        ] {
+            assert_eq!(Version::parse(v).unwrap().to_string(), v);
+        }
+    }
+
+    #[test]
+    fn increment() {
+        let cases = [
+            ("1.2.3", ReleaseType::Major, None, "2.0.0"),
+            ("1.2.3", ReleaseType::Minor, None, "1.3.0"),
+            ("1.2.3", ReleaseType::Patch, None, "1.2.4"),
+            ("1.2.3+build.1", ReleaseType::Patch, None, "1.2.4"),
+            ("1.2.3-tag", ReleaseType::Major, None, "2.0.0"),
+            ("1.2.0-0", ReleaseType::Patch, None, "1.2.0"),
+            ("1.2.3-4", ReleaseType::Major, None, "2.0.0"),
+            ("1.2.3-4", ReleaseType::Minor, None, "1.3.0"),
+            ("1.2.3-4", ReleaseType::Patch, None, "1.2.3"),
+            ("1.2.0-1", ReleaseType::Minor, None, "1.2.0"),
+            ("1.0.0-1", ReleaseType::Major, None, "1.0.0"),
+            ("1.2.4", ReleaseType::PreRelease, None, "1.2.5-0"),
+            ("1.2.3-0", ReleaseType::PreRelease, None, "1.2.3-1"),
+            (
+                "1.2.3-alpha.0",
+                ReleaseType::PreRelease,
+                None,
+                "1.2.3-alpha.1",
+            ),
+            (
+                "1.2.3-alpha.0.beta",
+                ReleaseType::PreRelease,
+                None,
+                "1.2.3-alpha.1.beta",
+            ),
+            (
+                "1.2.3-alpha.9.beta",
+                ReleaseType::PreRelease,
+                None,
+                "1.2.3-alpha.10.beta",
+            ),
+            (
+                "1.2.3-alpha.10.0.beta",
+                ReleaseType::PreRelease,
+                None,
+                "1.2.3-alpha.10.1.beta",
+            ),
+            (
+                "1.2.3-alpha",
+                ReleaseType::PreRelease,
+                None,
+                "1.2.3-alpha.0",
+            ),
+            ("1.2.0", ReleaseType::PrePatch, None, "1.2.1-0"),
+            ("1.2.0-1", ReleaseType::PrePatch, None, "1.2.1-0"),
+            ("1.2.0", ReleaseType::PreMinor, None, "1.3.0-0"),
+            ("1.2.3-1", ReleaseType::PreMinor, None, "1.3.0-0"),
+            ("1.2.0", ReleaseType::PreMajor, None, "2.0.0-0"),
+            ("1.2.3-1", ReleaseType::PreMajor, None, "2.0.0-0"),
+            ("1.2.3-alpha", ReleaseType::Release, None, "1.2.3"),
+            ("1.2.3", ReleaseType::Major, Some("dev"), "2.0.0"),
+            ("1.2.3", ReleaseType::PrePatch, Some("rc"), "1.2.4-rc.0"),
+            (
+                "1.2.4-rc.0",
+                ReleaseType::PreRelease,
+                Some("rc"),
+                "1.2.4-rc.1",
+            ),
+            ("1.2.3", ReleaseType::PreRelease, Some("dev"), "1.2.4-dev.0"),
+            (
+                "1.2.3-dev.1",
+                ReleaseType::PreRelease,
+                Some("alpha"),
+                "1.2.3-alpha.0",
+            ),
+            (
+                "1.2.3-dev.bar",
+                ReleaseType::PreRelease,
+                Some("dev"),
+                "1.2.3-dev.0",
+            ),
+            ("1.2.0-1", ReleaseType::PrePatch, Some("dev"), "1.2.1-dev.0"),
+            ("1.2.3-1", ReleaseType::PreMajor, Some("dev"), "2.0.0-dev.0"),
+            (
+                "1.2.3-beta.1",
+                ReleaseType::PreRelease,
+                Some("beta.1"),
+                "1.2.3-beta.1.0",
+            ),
+            (
+                "1.2.3-beta.1.0",
+                ReleaseType::PreRelease,
+                Some("beta.1"),
+                "1.2.3-beta.1.1",
+            ),
+        ];
+
+        for (from, release, identifier, expected) in cases {
+            let mut version = Version::parse(from).unwrap();
+            version.inc(release, identifier).unwrap();
+            assert_eq!(version.to_string(), expected, "{from} {release}");
+        }
+
+        let mut version = Version::parse("1.2.3").unwrap();
+        assert_eq!(
+            version.inc(ReleaseType::Release, None),
+            Err(IncError::NotPreRelease)
+        );
+        for identifier in [".a", "a..b", "01", "é"] {
+            assert_eq!(
+                version.inc(ReleaseType::PreRelease, Some(identifier)),
+                Err(IncError::InvalidIdentifier(identifier.to_owned()))
+            );
+        }
+        assert_eq!(version.to_string(), "1.2.3");
+    }
+
+    #[test]
+    fn difference() {
+        let cases = [
+            ("1.2.3", "0.2.3", Some(ReleaseType::Major)),
+            ("0.2.3", "1.2.3", Some(ReleaseType::Major)),
+            ("1.4.5", "0.2.3", Some(ReleaseType::Major)),
+            ("1.2.3", "2.0.0-pre", Some(ReleaseType::PreMajor)),
+            ("1.2.3", "1.3.3", Some(ReleaseType::Minor)),
+            ("1.0.1", "1.1.0-pre", Some(ReleaseType::PreMinor)),
+            ("1.2.3", "1.2.4", Some(ReleaseType::Patch)),
+            ("1.2.3", "1.2.4-pre", Some(ReleaseType::PrePatch)),
+            ("0.0.1", "0.0.1-pre", Some(ReleaseType::Patch)),
+            ("0.0.1", "0.0.1-pre.2", Some(ReleaseType::Patch)),
+            ("1.1.0", "1.1.0-pre", Some(ReleaseType::Minor)),
+            ("1.1.0-pre.1", "1.1.0-pre.2", Some(ReleaseType::PreRelease)),
+            ("1.0.0", "1.0.0", None),
+            ("1.0.0-1", "1.0.0-1", None),
+            ("1.0.0+build.1", "1.0.0", None),
+            ("0.0.2-1", "0.0.2", Some(ReleaseType::Patch)),
+            ("0.0.2-1", "0.0.3", Some(ReleaseType::Patch)),
+            ("0.0.2-1", "0.1.0", Some(ReleaseType::Minor)),
+            ("0.0.2-1", "1.0.0", Some(ReleaseType::Major)),
+            ("0.1.0-1", "0.1.0", Some(ReleaseType::Minor)),
+            ("1.0.0-1", "1.0.0", Some(ReleaseType::Major)),
+            ("1.0.0-1", "1.1.1", Some(ReleaseType::Major)),
+            ("1.0.0-1", "2.1.1", Some(ReleaseType::Major)),
+            ("1.0.1-1", "1.0.1", Some(ReleaseType::Patch)),
+            ("0.0.0-1", "0.0.0", Some(ReleaseType::Major)),
+            ("1.0.0-1", "2.0.0-1", Some(ReleaseType::PreMajor)),
+            ("1.0.0-1", "1.1.0-1", Some(ReleaseType::PreMinor)),
+            ("1.0.0-1", "1.0.1-1", Some(ReleaseType::PrePatch)),
+        ];
+
+        for (left, right, expected) in cases {
+            let left = Version::parse(left).unwrap();
+            let right = Version::parse(right).unwrap();
+            assert_eq!(left.diff(&right), expected, "{left} {right}");
+            assert_eq!(right.diff(&left), expected, "{right} {left}");
+        }
+    }
+
+    #[test]
+    fn hyphenated_identifiers() {
+        let version = Version::parse("1.0.0-pre-2.x-y+build-7").unwrap();
+        assert_eq!(version.pre_release, ["pre-2", "x-y"]);
+        assert_eq!(version.metadata, ["build-7"]);
+
+        let version = Version::parse("1.0.0-v1.0-1").unwrap();
+        assert_eq!(version.pre_release, ["v1", "0-1"]);
+
+        let v = "1.0.0-";
+        let error = Version::parse(v).unwrap_err();
+        assert_eq!(error.kind(), &ErrorKind::UnexpectedEnd);
+        assert_eq!(error.span(), Span::at(6));
+        assert_eq!(error.expected(), &[Expected::Identifier]);
+    }
+
+    #[test]
+    fn loose() {
+        let loose = ParseOptions { loose: true };
+
+        for (v, expected) in [
+            ("v 1.2.3", "1.2.3"),
+            ("==1.2.3", "1.2.3"),
+            ("= v1.2.3", "1.2.3"),
+            ("v=1.2.3", "1.2.3"),
+            ("01.002.3", "1.2.3"),
+            ("1.2.3beta", "1.2.3-beta"),
+            ("1.2.3beta.1+build", "1.2.3-beta.1+build"),
+            ("1.2.03-rc-1", "1.2.3-rc-1"),
+        ] {
+            let version = Version::parse_with_options(v, &loose).unwrap();
+            assert_eq!(version.to_string(), expected, "{v}");
+            assert!(Version::parse(v).is_err(), "{v}");
+        }
+
+        for v in ["=v1.2.3", "v1.2.3"] {
+            assert_eq!(Version::parse(v).unwrap().to_string(), "1.2.3");
+        }
+
+        for v in ["1.2.3.4", "version 2", "1.2.x"] {
+            assert!(Version::parse_with_options(v, &loose).is_err(), "{v}");
+        }
+    }
+
+    #[test]
+    fn coerce() {
+        let too_long = "1".repeat(17);
+        let cases = [
+            (".1", Some("1.0.0")),
+            (".1.", Some("1.0.0")),
+            ("..1", Some("1.0.0")),
+            (".1.1", Some("1.1.0")),
+            ("1.", Some("1.0.0")),
+            ("1.0", Some("1.0.0")),
+            ("0", Some("0.0.0")),
+            ("0.1.0", Some("0.1.0")),
+            ("1.2.3.4", Some("1.2.3")),
+            ("4.6.3.9.2-alpha2", Some("4.6.3")),
+            ("1.2.3-rc.1+build", Some("1.2.3")),
+            ("version 2", Some("2.0.0")),
+            ("v2", Some("2.0.0")),
+            ("v3.4 replaces v3.3.1", Some("3.4.0")),
+            ("1.2.3 - 2.0.0", Some("1.2.3")),
+            ("", None),
+            (".", None),
+            ("version one", None),
+            (too_long.as_str(), None),
+        ];
+
+        for (input, expected) in cases {
+            assert_eq!(
+                Version::coerce(input).map(|v| v.to_string()),
+                expected.map(str::to_owned),
+                "{input}"
+            );
+        }
+
+        let input = format!("{too_long}.2.3");
+        assert_eq!(Version::coerce(&input).unwrap().to_string(), "2.3.0");
+    }
+
+    #[test]
+    fn strict() {
+        for v in ["1.2.3", "1.0.0-rc.1+build.007", "1.0.0-0a.00a-1"] {
+            assert_eq!(Version::parse_strict(v).unwrap().to_string(), v);
+        }
+
+        let cases = [
+            ("1", ErrorKind::UnexpectedEnd, Span::at(1)),
+            ("1.2-beta", ErrorKind::UnexpectedToken, Span::new(3, 4)),
+            ("v1.2.3", ErrorKind::UnexpectedToken, Span::new(0, 1)),
+            ("=1.2.3", ErrorKind::UnexpectedToken, Span::new(0, 1)),
+            (" 1.2.3", ErrorKind::UnexpectedToken, Span::new(0, 1)),
+            ("1.2.3-01", ErrorKind::LeadingZero, Span::new(6, 8)),
+            ("1.2.3-rc.00", ErrorKind::LeadingZero, Span::new(9, 11)),
+            ("1.2.3-é", ErrorKind::InvalidCharacter('é'), Span::new(6, 8)),
+            ("1.2.3+é", ErrorKind::InvalidCharacter('é'), Span::new(6, 8)),
+        ];
+        for (v, kind, span) in cases {
+            let error = Version::parse_strict(v).unwrap_err();
+            assert_eq!((error.kind(), error.span()), (&kind, span), "{v}");
+            assert!(Version::parse(v).is_ok(), "{v}");
+        }
+    }
+
+    // The regular expression test corpus published with semver.org.
+    #[test]
+    fn strict_corpus() {
+        let valid = [
+            "0.0.4",
+            "1.2.3",
+            "10.20.30",
+            "1.1.2-prerelease+meta",
+            "1.1.2+meta",
+            "1.1.2+meta-valid",
+            "1.0.0-alpha",
+            "1.0.0-beta",
+            "1.0.0-alpha.beta",
+            "1.0.0-alpha.beta.1",
+            "1.0.0-alpha.1",
+            "1.0.0-alpha0.valid",
+            "1.0.0-alpha.0valid",
+            "1.0.0-alpha-a.b-c-somethinglong+build.1-aef.1-its-okay",
+            "1.0.0-rc.1+build.1",
+            "2.0.0-rc.1+build.123",
+            "1.2.3-beta",
+            "10.2.3-DEV-SNAPSHOT",
+            "1.2.3-SNAPSHOT-123",
+            "1.0.0",
+            "2.0.0",
+            "1.1.7",
+            "2.0.0+build.1848",
+            "2.0.1-alpha.1227",
+            "1.0.0-alpha+beta",
+            "1.2.3----RC-SNAPSHOT.12.9.1--.12+788",
+            "1.2.3----R-S.12.9.1--.12+meta",
+            "1.2.3----RC-SNAPSHOT.12.9.1--.12",
+            "1.0.0+0.build.1-rc.10000aaa-kk-0.1",
+            "1.0.0-0A.is.legal",
+        ];
+        for v in valid {
+            let version = Version::parse_strict(v).unwrap_or_else(|e| panic!("{v}: {e}"));
+            assert_eq!(version.to_string(), v);
+        }
+
+        // Valid grammar, but the components do not fit the version fields.
+        let huge = "99999999999999999999999.999999999999999999.99999999999999999";
+        let error = Version::parse_strict(huge).unwrap_err();
+        assert_eq!(error.kind(), &ErrorKind::Overflow);
+        assert_eq!(error.fragment(), "99999999999999999999999");
+
+        let invalid = [
+            "1",
+            "1.2",
+            "1.2.3-0123",
+            "1.2.3-0123.0123",
+            "1.1.2+.123",
+            "+invalid",
+            "-invalid",
+            "-invalid+invalid",
+            "-invalid.01",
+            "alpha",
+            "alpha.beta",
+            "alpha.beta.1",
+            "alpha.1",
+            "alpha+beta",
+            "alpha_beta",
+            "alpha.",
+            "alpha..",
+            "beta",
+            "1.0.0-alpha_beta",
+            "-alpha.",
+            "1.0.0-alpha..",
+            "1.0.0-alpha..1",
+            "1.0.0-alpha...1",
+            "1.0.0-alpha....1",
+            "1.0.0-alpha.....1",
+            "1.0.0-alpha......1",
+            "1.0.0-alpha.......1",
+            "01.1.1",
+            "1.01.1",
+            "1.1.01",
+            "1.2.3.DEV",
+            "1.2-SNAPSHOT",
+            "1.2.31.2.3----RC-SNAPSHOT.12.09.1--..12+788",
+            "1.2-RC-SNAPSHOT",
+            "-1.0.3-gamma+b7718",
+            "+justmeta",
+            "9.8.7+meta+meta",
+            "9.8.7-whatever+meta+meta",
+            "99999999999999999999999.999999999999999999.99999999999999999----RC-SNAPSHOT.12.09.1--------------------------------..12",
+        ];
+        for v in invalid {
+            assert!(Version::parse_strict(v).is_err(), "{v}");
+        }
+    }
+
+    #[test]
+    fn errors() {
+        let error = Version::parse("  1.2..3 ").unwrap_err();
+        assert_eq!(error.span(), Span::new(6, 7));
+        assert_eq!(error.fragment(), ".");
+        assert_eq!(error.input(), "  1.2..3 ");
+
+        let error = Version::parse("1.2.3x").unwrap_err();
+        assert_eq!(error.fragment(), "3x");
+        assert_eq!(error.expected(), &[Expected::Number]);
+
+        let error = Version::parse("1.2.3 x").unwrap_err();
+        assert_eq!(
+            error.expected(),
+            &[Expected::Hyphen, Expected::Plus, Expected::End]
+        );
+
+        let error = Version::parse("1.").unwrap_err();
+        assert_eq!(error.kind(), &ErrorKind::UnexpectedEnd);
+        assert_eq!(error.expected(), &[Expected::Number]);
+
+        let error = Version::parse("v").unwrap_err();
+        assert_eq!(error.kind(), &ErrorKind::UnexpectedEnd);
+        assert_eq!(error.expected(), &[Expected::Version]);
+    }
+
+    #[test]
+    fn large_components() {
+        let version = Version::parse("2023101612345.0.0").unwrap();
+        assert_eq!(version.major, 2023101612345);
+        assert!(version > Version::parse("4294967296.0.0").unwrap());
+
+        let max = format!("1.{}.0", u64::MAX);
+        assert_eq!(Version::parse(&max).unwrap().to_string(), max);
+
+        let error = Version::parse("1.18446744073709551616.0").unwrap_err();
+        assert_eq!(error.kind(), &ErrorKind::Overflow);
+        assert_eq!(error.span(), Span::new(2, 22));
+        assert_eq!(
+            error.to_string(),
+            "number `18446744073709551616` at 2 is larger than 18446744073709551615"
+        );
+
+        let loose = ParseOptions { loose: true };
+        let error =
+            Version::parse_with_options("1.2.99999999999999999999beta", &loose).unwrap_err();
+        assert_eq!(error.kind(), &ErrorKind::Overflow);
+        assert_eq!(error.fragment(), "99999999999999999999");
+
+        let version = Version::parse("1.0.0-99999999999999999999999").unwrap();
+        assert_eq!(version.pre_release, ["99999999999999999999999"]);
+
+        let mut version = Version::parse(&format!("{}.0.0", u64::MAX)).unwrap();
+        assert_eq!(
+            version.inc(ReleaseType::Major, None),
+            Err(IncError::Overflow)
+        );
+        assert_eq!(version.major, u64::MAX);
     }
 }
@@ -1,9 +1,13 @@
-use super::ParseError;
+use smallvec::SmallVec;
 
-#[derive(Debug, PartialEq)]
-pub enum Token {
+use super::{ErrorKind, ParseError, Span};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TokenKind<'a> {
     Empty,
 
+    Space,
+
     Asterisk,
     Dot,
     Hyphen,
@@ -14,80 +18,140 @@ pub enum Token {
     LessEqual,
     Caret,
     Tilde,
+    Equal,
     Or,
-    Number(u32),
-    AlphaNumeric(String),
+    Number(u64),
+    AlphaNumeric(&'a str),
 }
 
-pub fn tokenize(input: &str) -> Result<Vec<Token>, ParseError> {
-    let mut input = input.chars().peekable();
-    let mut curr = input.next();
-    let mut tokens = vec![];
+#[derive(Clone, Debug, PartialEq)]
+pub struct Token<'a> {
+    pub kind: TokenKind<'a>,
+    pub span: Span,
+}
 
-    if let Some(c) = curr {
-        if c == '=' {
-            curr = input.next();
-        } else if c == 'v' {
-            curr = input.next();
-        }
-    }
+// Enough for a version with a pre-release and some build metadata, or for a
+// short range, without going to the heap.
+pub type Tokens<'a> = SmallVec<[Token<'a>; 16]>;
 
-    while curr.is_some() {
-        let c = curr.unwrap();
-        match c {
-            ' ' | ',' => (),
+pub fn tokenize(input: &str) -> Result<Tokens<'_>, ParseError> {
+    let mut input_chars = input.char_indices().peekable();
+    let mut tokens = Tokens::new();
 
-            '*' => tokens.push(Token::Asterisk),
-            '.' => tokens.push(Token::Dot),
-            '-' => tokens.push(Token::Hyphen),
-            '+' => tokens.push(Token::Plus),
+    while let Some((start, c)) = input_chars.next() {
+        let kind = match c {
+            c if c.is_whitespace() || c == ',' => {
+                while input_chars
+                    .peek()
+                    .is_some_and(|(_, c)| c.is_whitespace() || *c == ',')
+                {
+                    input_chars.next();
+                }
+                TokenKind::Space
+            }
 
-            '0' => tokens.push(Token::Number(c.to_digit(10).unwrap())),
+            '*' => TokenKind::Asterisk,
+            '.' => TokenKind::Dot,
+            '-' => TokenKind::Hyphen,
+            '+' => TokenKind::Plus,
 
-            '~' => tokens.push(Token::Tilde),
-            '^' => tokens.push(Token::Caret),
+            // `~>` is an old spelling of `~` that node-semver still accepts.
+            '~' => {
+                input_chars.next_if(|(_, c)| *c == '>');
+                TokenKind::Tilde
+            }
+            '=' => TokenKind::Equal,
+            '^' => TokenKind::Caret,
             '>' => {
-                if input.peek().is_some_and(|c| *c == '=') {
-                    input.next();
-                    tokens.push(Token::GreaterEqual);
+                if input_chars.next_if(|(_, c)| *c == '=').is_some() {
+                    TokenKind::GreaterEqual
                 } else {
-                    tokens.push(Token::Greater);
+                    TokenKind::Greater
                 }
             }
             '<' => {
-                if input.peek().is_some_and(|c| *c == '=') {
-                    input.next();
-                    tokens.push(Token::LessEqual);
+                if input_chars.next_if(|(_, c)| *c == '=').is_some() {
+                    TokenKind::LessEqual
                 } else {
-                    tokens.push(Token::Less);
+                    TokenKind::Less
                 }
             }
-            '|' if input.peek().is_some_and(|c| *c == '|') => {
-                input.next();
-                tokens.push(Token::Or);
+            '|' if input_chars.next_if(|(_, c)| *c == '|').is_some() => TokenKind::Or,
+
+            // The `v` of `v1.2.3` is kept apart so it can be skipped in front
+            // of any version, not only at the start of the input.
+            'v' if input_chars.peek().is_some_and(|(_, c)| c.is_ascii_digit()) => {
+                TokenKind::AlphaNumeric(&input[start..start + 1])
             }
 
-            n if n.is_alphanumeric() || n == '-' => {
-                let mut current_token = String::from(c);
-                while input
-                    .peek()
-                    .is_some_and(|c| c.is_alphanumeric() || n == '-')
-                {
-                    current_token.push(input.next().unwrap());
+            n if n.is_alphanumeric() => {
+                let mut end = start + n.len_utf8();
+                while let Some((i, c)) = input_chars.next_if(|(_, c)| c.is_alphanumeric()) {
+                    end = i + c.len_utf8();
                 }
+                let text = &input[start..end];
 
-                let token = match current_token.parse::<u32>() {
-                    Ok(number) => Token::Number(number),
-                    Err(_) => Token::AlphaNumeric(current_token),
-                };
-                tokens.push(token);
+                // Numbers with leading zeros stay as written, `01` is not `1`.
+                let leading_zero = text.len() > 1 && text.starts_with('0');
+                match text.parse::<u64>() {
+                    Ok(number) if !leading_zero => TokenKind::Number(number),
+                    _ => TokenKind::AlphaNumeric(text),
+                }
             }
 
-            _ => return Err(ParseError::InvalidToken(c)),
-        }
+            _ => {
+                let span = Span::new(start, start + c.len_utf8());
+                return Err(ParseError::new(ErrorKind::InvalidCharacter(c), span).with_input(input));
+            }
+        };
 
-        curr = input.next();
+        let end = input_chars.peek().map_or(input.len(), |(i, _)| *i);
+        tokens.push(Token {
+            kind,
+            span: Span::new(start, end),
+        });
     }
 
     Ok(tokens)
 }
+
+pub fn trim_spaces<'t, 'a>(mut tokens: &'t [Token<'a>]) -> &'t [Token<'a>] {
+    while let [first, rest @ ..] = tokens {
+        if first.kind != TokenKind::Space {
+            break;
+        }
+        tokens = rest;
+    }
+    while let [rest @ .., last] = tokens {
+        if last.kind != TokenKind::Space {
+            break;
+        }
+        tokens = rest;
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spans() {
+        let input = ">= 1.20.0-rc || ~v2";
+        let tokens = tokenize(input).unwrap();
+
+        let fragments = tokens
+            .iter()
+            .map(|t| &input[t.span.start..t.span.end])
+            .collect::<Vec<_>>();
+        assert_eq!(
+            fragments,
+            [">=", " ", "1", ".", "20", ".", "0", "-", "rc", " ", "||", " ", "~", "v", "2"]
+        );
+        assert_eq!(tokens[4].kind, TokenKind::Number(20));
+
+        let error = tokenize("1.2.3 | 2").unwrap_err();
+        assert_eq!(error.kind(), &ErrorKind::InvalidCharacter('|'));
+        assert_eq!(error.span(), Span::new(6, 7));
+    }
+}